@@ -1,13 +1,13 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Reply};
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Reply};
 use cw2::set_contract_version;
 
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::querier::{query_sale, query_sales, query_sales_owner, query_progress};
-use crate::state::{CONFIG, Config, ADMIN, OWNER_CACHE, TOKEN_ADDRESS_BY_PRESALE_ID, PRESALE_ID};
-use crate::handler::{execute_update_config, execute_create_presale, execute_participate, execute_claim, execute_refund};
+use crate::querier::{query_sale, query_sales, query_sales_owner, query_progress, query_vesting, query_referral_code, query_referral_rewards, query_escrow, query_whitelist, query_verify_merkle_proof, query_contribution_limit, query_validate_sell_param, query_currency_whitelist, query_currency, query_allocation, query_current_price, query_simulate_buy, query_is_winner, query_tiers, query_tier_registration};
+use crate::state::{CONFIG, Config, ADMIN, PENDING_INSTANTIATE, PENDING_LIQUIDITY, PRESALE, REPLY_SEQ, TOKEN_ADDRESS_BY_PRESALE_ID, PRESALE_ID};
+use crate::handler::{execute_update_config, execute_create_presale, execute_participate, execute_claim, execute_refund, execute_register_referral_code, execute_claim_referral_rewards, execute_receive, assert_native_funds, execute_cancel_sale, execute_update_whitelist, execute_update_merkle_root, execute_update_currency, execute_settle_auction, execute_register_ticket, execute_commit_seed, execute_reveal_seed, execute_draw_winners, execute_update_tiers, execute_register_tier, reply_provide_liquidity};
 use cw_utils::parse_reply_instantiate_data;
 
 // version info for migration info
@@ -22,14 +22,18 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     let config = Config {
-        min_cap: msg.min_cap,
         min_token_sale_amt: msg.min_token_sale_amt,
         token_code_id: msg.token_code_id,
-        fee_percentage: msg.fee_percentage
+        fee_percentage: msg.fee_percentage,
+        token_fee_percentage: msg.token_fee_percentage,
+        fee_collector: deps.api.addr_validate(&msg.fee_collector)?,
+        referral: msg.referral,
     };
+    config.assert_valid_fees()?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     CONFIG.save(deps.storage, &config)?;
     PRESALE_ID.save(deps.storage, &1)?;
+    REPLY_SEQ.save(deps.storage, &0)?;
     ADMIN.set(deps, Some(info.sender.clone()))?;
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -44,11 +48,30 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateConfigMsg{ min_cap, min_token_sale_amt, token_code_id, fee_percentage} => execute_update_config(deps, info, min_cap, min_token_sale_amt, token_code_id, fee_percentage),
-        ExecuteMsg::CreatePresaleMsg{ amount, param} => execute_create_presale(deps, env, info.clone(), info.sender.clone(), amount, param),
-        ExecuteMsg::ParticipateMsg{ id, cur , allow_partial} => execute_participate(deps, env, info.sender, id, cur, allow_partial),
+        ExecuteMsg::UpdateConfigMsg{ min_token_sale_amt, token_code_id, fee_percentage, token_fee_percentage, fee_collector, referral} => execute_update_config(deps, info, min_token_sale_amt, token_code_id, fee_percentage, token_fee_percentage, fee_collector, referral),
+        ExecuteMsg::CreatePresaleMsg{ amount, param} => execute_create_presale(deps, env, info.sender, amount, param),
+        ExecuteMsg::ParticipateMsg{ id, cur , allow_partial, referral_code, merkle_proof, voucher, min_tokens_out, max_price} => {
+            assert_native_funds(&info, &cur)?;
+            execute_participate(deps, env, info.sender, id, cur, allow_partial, referral_code, merkle_proof, voucher, min_tokens_out, max_price)
+        }
         ExecuteMsg::ClaimMsg{ id } => execute_claim(deps, env, info, id),
-        ExecuteMsg::RefundMsg{ id } => execute_refund(deps, env, info, id)
+        ExecuteMsg::RefundMsg{ id } => execute_refund(deps, env, info, id),
+        ExecuteMsg::CancelSale{ id, reason } => execute_cancel_sale(deps, env, info, id, reason),
+        ExecuteMsg::AddToWhitelist{ id, round, addresses } => execute_update_whitelist(deps, info, id, round, addresses, true),
+        ExecuteMsg::RemoveFromWhitelist{ id, round, addresses } => execute_update_whitelist(deps, info, id, round, addresses, false),
+        ExecuteMsg::UpdateMerkleRoot{ id, merkle_root } => execute_update_merkle_root(deps, env, info, id, merkle_root),
+        ExecuteMsg::RegisterReferralCode{ code } => execute_register_referral_code(deps, info, code),
+        ExecuteMsg::ClaimReferralRewards{} => execute_claim_referral_rewards(deps, info),
+        ExecuteMsg::SettleAuction{ id } => execute_settle_auction(deps, env, id),
+        ExecuteMsg::RegisterTicket{ id } => execute_register_ticket(deps, env, info, id),
+        ExecuteMsg::CommitSeed{ id, commitment } => execute_commit_seed(deps, env, info, id, commitment),
+        ExecuteMsg::RevealSeed{ id, seed } => execute_reveal_seed(deps, env, info, id, seed),
+        ExecuteMsg::DrawWinners{ id } => execute_draw_winners(deps, id),
+        ExecuteMsg::UpdateTiers{ staking_contract, tiers } => execute_update_tiers(deps, info, staking_contract, tiers),
+        ExecuteMsg::RegisterTier{ id } => execute_register_tier(deps, env, info, id),
+        ExecuteMsg::AddCurrency{ info: cur_info, min_soft_cap } => execute_update_currency(deps, info, cur_info, Some(min_soft_cap)),
+        ExecuteMsg::RemoveCurrency{ info: cur_info } => execute_update_currency(deps, info, cur_info, None),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
     }
 }
 
@@ -56,20 +79,20 @@ pub fn execute(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Admin {} => to_binary(&ADMIN.query_admin(deps)?),
-        QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
-        QueryMsg::Sale { id } => to_binary(&query_sale(deps, env, id)?),
+        QueryMsg::Admin {} => to_json_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Sale { id } => to_json_binary(&query_sale(deps, env, id)?),
         QueryMsg::Sales {
             start_after,
             limit,
             is_ascending,
-        } => to_binary(&query_sales(deps, env, start_after, limit, is_ascending)?),
+        } => to_json_binary(&query_sales(deps, env, start_after, limit, is_ascending)?),
         QueryMsg::SalesOwner {
             address,
             start_after,
             limit,
             is_ascending,
-        } => to_binary(&query_sales_owner(
+        } => to_json_binary(&query_sales_owner(
             deps,
             env,
             address,
@@ -77,24 +100,50 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             limit,
             is_ascending,
         )?),
-        QueryMsg::Progress { id, address } => to_binary(&query_progress(deps, id, address)?),
+        QueryMsg::Progress { id, address } => to_json_binary(&query_progress(deps, id, address)?),
+        QueryMsg::Vesting { id, address } => to_json_binary(&query_vesting(deps, env, id, address)?),
+        QueryMsg::ReferralCode { code } => to_json_binary(&query_referral_code(deps, code)?),
+        QueryMsg::ReferralRewards { address } => to_json_binary(&query_referral_rewards(deps, address)?),
+        QueryMsg::Escrow { id } => to_json_binary(&query_escrow(deps, env, id)?),
+        QueryMsg::Whitelist { id, round, start_after, limit } => to_json_binary(&query_whitelist(deps, id, round, start_after, limit)?),
+        QueryMsg::VerifyMerkleProof { id, address, proof } => to_json_binary(&query_verify_merkle_proof(deps, id, address, proof)?),
+        QueryMsg::ContributionLimit { id, address } => to_json_binary(&query_contribution_limit(deps, env, id, address)?),
+        QueryMsg::ValidateSellParam { owner, token, param } => to_json_binary(&query_validate_sell_param(deps, env, owner, token, param)?),
+        QueryMsg::Allocation { id, address } => to_json_binary(&query_allocation(deps, env, id, address)?),
+        QueryMsg::CurrentPrice { id } => to_json_binary(&query_current_price(deps, env, id)?),
+        QueryMsg::SimulateBuy { id, cur_amount } => to_json_binary(&query_simulate_buy(deps, env, id, cur_amount)?),
+        QueryMsg::CurrencyWhitelist {} => to_json_binary(&query_currency_whitelist(deps)?),
+        QueryMsg::Currency { info } => to_json_binary(&query_currency(deps, info)?),
+        QueryMsg::IsWinner { id, address } => to_json_binary(&query_is_winner(deps, id, address)?),
+        QueryMsg::Tiers {} => to_json_binary(&query_tiers(deps)?),
+        QueryMsg::TierRegistration { id, address } => to_json_binary(&query_tier_registration(deps, id, address)?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        1 => {
-            let contract_addr = deps
-                .api
-                .addr_validate(&parse_reply_instantiate_data(msg).unwrap().contract_address)?;
-            let presale_id = PRESALE_ID.load(deps.storage)?;
-            TOKEN_ADDRESS_BY_PRESALE_ID.save(deps.storage,presale_id, &contract_addr)?;
-            OWNER_CACHE.remove(deps.storage);
+    if let Some(id) = PENDING_LIQUIDITY.may_load(deps.storage, msg.id)? {
+        PENDING_LIQUIDITY.remove(deps.storage, msg.id);
+        return reply_provide_liquidity(deps, id, msg.result);
+    }
 
+    let id = PENDING_INSTANTIATE
+        .may_load(deps.storage, msg.id)?
+        .ok_or(ContractError::InvalidReplyId)?;
+    PENDING_INSTANTIATE.remove(deps.storage, msg.id);
 
-            Ok(Response::new().add_attribute("token_addr", contract_addr))
-        }
-        _ => Err(ContractError::InvalidReplyId)?,
-    }
-}
\ No newline at end of file
+    let contract_addr = deps
+        .api
+        .addr_validate(&parse_reply_instantiate_data(msg)?.contract_address)?;
+
+    PRESALE.update(deps.storage, id, |sale| -> StdResult<_> {
+        let mut sale = sale.ok_or_else(|| StdError::not_found("sale"))?;
+        sale.token_addr = contract_addr.clone();
+        Ok(sale)
+    })?;
+    TOKEN_ADDRESS_BY_PRESALE_ID.save(deps.storage, id, &contract_addr)?;
+
+    Ok(Response::new()
+        .add_attribute("id", id.to_string())
+        .add_attribute("token_addr", contract_addr))
+}
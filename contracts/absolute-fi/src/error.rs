@@ -1,5 +1,6 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::{OverflowError, StdError, Uint128};
 use cw_controllers::AdminError;
+use cw_utils::{ParseReplyError, PaymentError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,15 +8,30 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Admin: {0}")]
     Admin(#[from] AdminError),
 
+    #[error("Payment: {0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Reply: {0}")]
+    ParseReply(#[from] ParseReplyError),
+
     #[error("Unauthorized")]
     Unauthorized,
 
     #[error("Fee amount mismatched, expected {0} found {1}")]
     FeeMismatch(Uint128, Uint128),
 
+    #[error("Fee percentage must be lteq than {0}")]
+    InvalidFeePercentage(Uint128),
+
+    #[error("Sent funds mismatched, expected {0} found {1}")]
+    FundsMismatch(Uint128, Uint128),
+
     #[error("Only sale owner is authorized")]
     OnlySaleOwner,
 
@@ -37,6 +53,30 @@ pub enum ContractError {
     #[error("Refund: {0}")]
     Refund(String),
 
+    #[error("Referral: {0}")]
+    Referral(String),
+
+    #[error("Cancel: {0}")]
+    Cancel(String),
+
+    #[error("Lottery: {0}")]
+    Lottery(String),
+
+    #[error("Tier: {0}")]
+    Tier(String),
+
+    #[error("Contribution exceeds the maximum of {0} per account")]
+    AboveMaxContribution(Uint128),
+
+    #[error("Contribution is below the minimum of {0} per account")]
+    BelowMinContribution(Uint128),
+
+    #[error("This sale reached its maximum of {0} participants")]
+    ParticipantLimit(u32),
+
+    #[error("Tokens out below minimum, expected {0} found {1}")]
+    Slippage(Uint128, Uint128),
+
     #[error("This sale is not started yet")]
     NotStarted,
 
@@ -49,7 +89,7 @@ pub enum ContractError {
     #[error("This sale is already filled")]
     AlreadyFilled,
 
-    #[error("This sale is already filled")]
+    #[error("Unknown reply id")]
     InvalidReplyId,
 
     #[error("This sale amount soft cap has not been reached, please `refund` instead")]
@@ -58,6 +98,9 @@ pub enum ContractError {
     #[error("This sale amount soft cap has been reached, please `claim` instead")]
     Ended,
 
+    #[error("This sale has been cancelled, please `refund` instead")]
+    Cancelled,
+
     #[error("{0}")]
     Custom(String),
 }
@@ -83,6 +126,22 @@ impl ContractError {
         Self::Refund(description.into())
     }
 
+    pub fn referral(description: impl Into<String>) -> Self {
+        Self::Referral(description.into())
+    }
+
+    pub fn cancel(description: impl Into<String>) -> Self {
+        Self::Cancel(description.into())
+    }
+
+    pub fn lottery(description: impl Into<String>) -> Self {
+        Self::Lottery(description.into())
+    }
+
+    pub fn tier(description: impl Into<String>) -> Self {
+        Self::Tier(description.into())
+    }
+
     pub fn custom(description: impl Into<String>) -> Self {
         Self::Custom(description.into())
     }
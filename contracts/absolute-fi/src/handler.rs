@@ -1,22 +1,53 @@
 use cosmwasm_std::{
-  to_binary, Addr, Coin, Decimal, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
-  WasmMsg, CosmosMsg, to_json_binary, SubMsg,
+  from_json, to_json_binary, to_json_vec, Addr, Binary, Coin, CosmosMsg, Decimal, DepsMut, Env, Fraction, MessageInfo, Order, Response, StdError, StdResult, Storage,
+  Uint128, WasmMsg, SubMsg, SubMsgResult,
 };
-use cw20::{Cw20ExecuteMsg, Cw20Coin, Logo, MinterResponse, };
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw_utils::must_pay;
+use sha2::{Digest, Sha256};
 use cw20_base::msg::{InstantiateMarketingInfo, InstantiateMsg as Cw20InstantiateMsg};
-use cw_asset::{Asset, AssetInfoBase, AssetInfoUnchecked, AssetUnchecked};
+use cw_asset::{Asset, AssetInfo, AssetInfoBase, AssetInfoUnchecked};
+use cw_storage_plus::Bound;
+
+use absolute_fi_staking::{
+  msg::{QueryMsg as StakingQueryMsg, StakedResponse},
+  state::HeightOrTime,
+};
 
 use crate::{
+  msg::{PairAsset, PairAssetInfo, PairExecuteMsg, ReceiveMsg},
   state::{
-      ADMIN, CONFIG, PRESALE_ID,
+      ADMIN, CONFIG, CURRENCY_NATIVE, CURRENCY_TOKEN, PENDING_INSTANTIATE, PENDING_LIQUIDITY, PRESALE_ID, REPLY_SEQ,
       PRESALE,
       PRESALE_PROGRESS,
       PRESALE_PARTICIPANT_BY_PRESALE_ID,
       PRESALE_WL,
+      REFERRAL_CODE,
+      REFERRAL_OF,
+      REFERRAL_PENDING,
+      REFERRAL_REWARD,
       SALE_OWNER,
+      ReferralBase,
+      ReferralConfig,
+      ReferralReward,
       SaleProgress,
+      SaleProgressPersonal,
+      SaleType,
       SellParam,
+      AUCTION_BID,
+      LOTTERY_ENTRANT,
+      LOTTERY_TICKET,
+      LOTTERY_WINNER,
+      TIER_CONFIG,
+      TIER_WEIGHT,
+      Lottery,
+      Tier,
+      TierConfig,
       Sale,
+      MerkleProof,
+      SignedVoucher,
+      VOUCHER_NONCE,
+      assert_valid_merkle_root,
       SaleStatus,
       TOKEN_ADDRESS_BY_PRESALE_ID
   },
@@ -24,91 +55,335 @@ use crate::{
 };
 
 
+const MAX_REFERRAL_CODE_LEN: usize = 32;
+// maximum bids walked per auction settlement call
+const MAX_BID_SETTLE: usize = 50;
+// maximum lottery winners drawn per call
+const MAX_WINNER_DRAW: u32 = 50;
+
 #[allow(clippy::too_many_arguments)]
 pub fn execute_update_config(
   deps: DepsMut,
   info: MessageInfo,
-  min_cap: [Uint128; 2],
   min_token_sale_amt: Uint128,
   token_code_id: u64,
   fee_percentage: Uint128,
+  token_fee_percentage: Uint128,
+  fee_collector: String,
+  referral: ReferralConfig,
 ) -> Result<Response, ContractError> {
   ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
 
   let mut cfg = CONFIG.load(deps.storage)?;
-  cfg.min_cap= min_cap;
   cfg.min_token_sale_amt= min_token_sale_amt;
   cfg.token_code_id= token_code_id;
   cfg.fee_percentage= fee_percentage;
-
+  cfg.token_fee_percentage= token_fee_percentage;
+  cfg.fee_collector= deps.api.addr_validate(&fee_collector)?;
+  cfg.referral= referral;
+  cfg.assert_valid_fees()?;
 
   CONFIG.save(deps.storage, &cfg)?;
 
   Ok(Response::new().add_attribute("action", "update_config"))
 }
 
+pub fn execute_update_currency(
+    deps: DepsMut,
+    info: MessageInfo,
+    cur_info: AssetInfoUnchecked,
+    min_soft_cap: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let cur_info = cur_info.check(deps.api, None)?;
+    match (&cur_info, min_soft_cap) {
+        (AssetInfo::Native(denom), Some(min)) => CURRENCY_NATIVE.save(deps.storage, denom, &min)?,
+        (AssetInfo::Native(denom), None) => CURRENCY_NATIVE.remove(deps.storage, denom),
+        (AssetInfo::Cw20(addr), Some(min)) => CURRENCY_TOKEN.save(deps.storage, addr, &min)?,
+        (AssetInfo::Cw20(addr), None) => CURRENCY_TOKEN.remove(deps.storage, addr),
+        _ => Err(ContractError::custom("Only native and cw20 currencies are supported"))?,
+    }
+
+    Ok(Response::new()
+        .add_attribute(
+            "action",
+            match min_soft_cap {
+                Some(_) => "add_currency",
+                None => "remove_currency",
+            },
+        )
+        .add_attribute("currency", cur_info.to_string()))
+}
+
+pub fn execute_update_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    staking_contract: String,
+    tiers: Vec<Tier>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let config = TierConfig {
+        staking_contract: deps.api.addr_validate(&staking_contract)?,
+        tiers,
+    };
+    config.assert_valid()?;
+    TIER_CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_tiers")
+        .add_attribute("staking_contract", config.staking_contract)
+        .add_attribute("tiers", config.tiers.len().to_string()))
+}
+
 pub fn execute_create_presale(
-  deps: DepsMut,
+  mut deps: DepsMut,
   env: Env,
-  info: MessageInfo,
   owner: Addr,
-  amount: Uint128,
+  _amount: Uint128,
   param: SellParam,
 ) -> Result<Response, ContractError> {
   let config = CONFIG.load(deps.storage)?;
-  let id = PRESALE_ID.load(deps.storage)?;
-
-
-  param.assert_start_end(env.block.time.seconds())?;
-
-  PRESALE.save(
-      deps.storage,
-      id,
-      &Sale::from_param(
-          deps.api,
-          param.clone(),
-          id,
-          env.block.time.seconds(),
-          owner.clone(),
-          info.sender.clone(),
-      )?,
-  )?;
+
+  // token address is patched into the sale once the instantiation replies
+  let (sale, token_escrowed) = save_presale(deps.branch(), &env, &owner, owner.clone(), param.clone(), None)?;
+  let token_msg = token_instantiate_msg(&param, &env.contract.address, token_escrowed);
+
+  let reply_id = REPLY_SEQ.load(deps.storage)? + 1;
+  REPLY_SEQ.save(deps.storage, &reply_id)?;
+  PENDING_INSTANTIATE.save(deps.storage, reply_id, &sale.id)?;
+
   let messages =SubMsg::reply_on_success(WasmMsg::Instantiate {
     admin: None ,
     code_id: config.token_code_id,
-    msg: to_binary(&Cw20InstantiateMsg {
+    msg: to_json_binary(&token_msg)?,
+        funds: vec![],
+        label: "Absolute Fi".to_string()
+    }, reply_id);
+
+  Ok(Response::new()
+        .add_submessage(messages)
+        .add_attribute("action", "sell")
+        .add_attribute("id", sale.id.to_string())
+        .add_attribute("owner", owner)
+        .add_attribute("token_escrowed", token_escrowed))
+}
+
+/// Creates a sale for an already deployed cw20, `amount` sent is the sale inventory.
+pub fn execute_create_presale_with_token(
+    mut deps: DepsMut,
+    env: Env,
+    owner: Addr,
+    token_addr: Addr,
+    amount: Uint128,
+    param: SellParam,
+) -> Result<Response, ContractError> {
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&token_addr, &Cw20QueryMsg::TokenInfo {})?;
+    let param = SellParam {
+        token_name: token_info.name,
+        token_symbol: token_info.symbol,
+        token_decimals: token_info.decimals,
+        ..param
+    };
+
+    let (sale, token_escrowed) =
+        save_presale(deps.branch(), &env, &owner, token_addr.clone(), param, Some(amount))?;
+    TOKEN_ADDRESS_BY_PRESALE_ID.save(deps.storage, sale.id, &token_addr)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "sell")
+        .add_attribute("id", sale.id.to_string())
+        .add_attribute("owner", owner)
+        .add_attribute("token_addr", token_addr)
+        .add_attribute("token_escrowed", token_escrowed))
+}
+
+/// Approves the pair for the cw20 side(s) and provides `token` and `cur` as
+/// liquidity, native currency being sent along. The provision replies to
+/// `reply_id` so a failing pair cannot block the settlement.
+fn provide_liquidity_msgs(
+    pair: &Addr,
+    token: Asset,
+    cur: Asset,
+    receiver: &Addr,
+    reply_id: u64,
+) -> StdResult<Vec<SubMsg>> {
+    let mut msgs = vec![];
+    let mut funds = vec![];
+    let mut assets = vec![];
+
+    for asset in [token, cur].iter() {
+        let info = match &asset.info {
+            AssetInfo::Cw20(contract_addr) => {
+                msgs.push(SubMsg::new(WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                        spender: pair.to_string(),
+                        amount: asset.amount,
+                        expires: None,
+                    })?,
+                    funds: vec![],
+                }));
+                PairAssetInfo::Token {
+                    contract_addr: contract_addr.to_string(),
+                }
+            }
+            AssetInfo::Native(denom) => {
+                funds.push(Coin::new(asset.amount.u128(), denom));
+                PairAssetInfo::NativeToken { denom: denom.clone() }
+            }
+            _ => Err(StdError::generic_err("Only native and cw20 assets can be paired"))?,
+        };
+        assets.push(PairAsset {
+            info,
+            amount: asset.amount,
+        });
+    }
+
+    msgs.push(SubMsg::reply_always(
+        WasmMsg::Execute {
+            contract_addr: pair.to_string(),
+            msg: to_json_binary(&PairExecuteMsg::ProvideLiquidity {
+                assets,
+                slippage_tolerance: None,
+                receiver: Some(receiver.to_string()),
+            })?,
+            funds,
+        },
+        reply_id,
+    ));
+
+    Ok(msgs)
+}
+
+/// Builds the sale token instantiation, its metadata was checked against the
+/// cw20-base rules with the sell param.
+fn token_instantiate_msg(param: &SellParam, holder: &Addr, amount: Uint128) -> Cw20InstantiateMsg {
+    let non_empty = |s: &String| (!s.is_empty()).then(|| s.clone());
+    Cw20InstantiateMsg {
         name: param.token_name.clone(),
         symbol: param.token_symbol.clone(),
-        decimals: 6,
+        decimals: param.token_decimals,
         initial_balances: vec![Cw20Coin {
-            address: info.sender.clone().to_string(),
-            amount: param.clone().hard_cap,
+            address: holder.to_string(),
+            amount,
         }],
+        // fixed supply, everything is escrowed at instantiation
         mint: None,
-        marketing: None 
-        })?,
-        funds: vec![],
-        label: "Absolute Fi".to_string()
-    }, 1);
+        marketing: Some(InstantiateMarketingInfo {
+            project: non_empty(&param.token_project),
+            description: non_empty(&param.token_description),
+            marketing: param.token_marketing.clone(),
+            logo: param.token_logo.clone(),
+        }),
+    }
+}
+
+/// Validates and stores a new sale, returning it with its escrowed token amount.
+/// `sent` is the inventory already transferred, `None` when the token is minted for the sale.
+fn save_presale(
+    deps: DepsMut,
+    env: &Env,
+    owner: &Addr,
+    token_addr: Addr,
+    param: SellParam,
+    sent: Option<Uint128>,
+) -> Result<(Sale, Uint128), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let id = PRESALE_ID.load(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    param.validate(deps.as_ref(), owner, sent.is_none(), now)?;
+
+    let sale = Sale::from_param(deps.api, &config, param, id, now, owner.clone(), token_addr)?;
+
+    // the whole supply is held by the launchpad until claimed or refunded
+    let token_required = sale.token_required();
+    let token_escrowed = match sent {
+        Some(amount) => {
+            (amount >= token_required)
+                .then_some(())
+                .ok_or_else(|| {
+                    ContractError::sell(format!("Sent token amount must be gteq than {}", token_required))
+                })?;
+            amount
+        }
+        None => token_required,
+    };
+
+    PRESALE.save(deps.storage, id, &sale)?;
     PRESALE_ID.save(deps.storage, &(id + 1))?;
-    PRESALE_PROGRESS.save(deps.storage, id, &SaleProgress::default())?;
-    SALE_OWNER.save(deps.storage, (&owner, id), &())?;
+    PRESALE_PROGRESS.save(
+        deps.storage,
+        id,
+        &SaleProgress {
+            token_escrowed,
+            round_raised: vec![Uint128::zero(); sale.round_count()],
+            ..SaleProgress::default()
+        },
+    )?;
+    SALE_OWNER.save(deps.storage, (owner, id), &())?;
 
-  Ok(Response::new()
-        .add_submessage(messages)
-        .add_attribute("action", "sell")
-        .add_attribute("id", id.to_string())
-        .add_attribute("owner", owner))
+    Ok((sale, token_escrowed))
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::ParticipateHook { id, allow_partial, referral_code, merkle_proof, voucher, min_tokens_out, max_price } => execute_participate(
+            deps,
+            env,
+            sender,
+            id,
+            Asset::cw20(info.sender, wrapper.amount),
+            allow_partial,
+            referral_code,
+            merkle_proof,
+            voucher,
+            min_tokens_out,
+            max_price,
+        ),
+        ReceiveMsg::CreatePresale { param } => {
+            execute_create_presale_with_token(deps, env, sender, info.sender, wrapper.amount, param)
+        }
+    }
 }
 
+/// Checks that `info.funds` carries exactly the native `cur` and nothing else.
+pub fn assert_native_funds(info: &MessageInfo, cur: &Asset) -> Result<(), ContractError> {
+    let denom = match &cur.info {
+        AssetInfo::Native(denom) => denom,
+        _ => Err(ContractError::buy("Cw20 currency must be sent through `receive`"))?,
+    };
+
+    let sent = must_pay(info, denom)?;
+    (sent == cur.amount)
+        .then_some(())
+        .ok_or(ContractError::FundsMismatch(cur.amount, sent))
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_participate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     buyer: Addr,
     id: u64,
     cur: Asset,
     allow_partial: bool,
+    referral_code: Option<String>,
+    merkle_proof: Option<MerkleProof>,
+    voucher: Option<SignedVoucher>,
+    min_tokens_out: Option<Uint128>,
+    max_price: Option<Decimal>,
 ) -> Result<Response, ContractError> {
     let sale = PRESALE.load(deps.storage, id)?;
     let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
@@ -117,12 +392,13 @@ pub fn execute_participate(
         .unwrap_or_default();
 
     let mut msgs = vec![];
+    let mut attrs = vec![];
 
     (cur.info == sale.cur_info)
-        .then(|| ())
+        .then_some(())
         .ok_or_else(|| ContractError::buy("Currency token mismatched"))?;
     (sale.owner != buyer)
-        .then(|| ())
+        .then_some(())
         .ok_or_else(|| ContractError::buy("Sale owner cannot participate"))?;
 
     match sale.status(&sale_prog, env.block.time.seconds()) {
@@ -135,125 +411,336 @@ pub fn execute_participate(
         SaleStatus::Filled => {
             Err(ContractError::AlreadyFilled)?;
         }
-        SaleStatus::Ongoing => {
-            let token_bought_amt = cur
-                .amount
-                .multiply_ratio(sale.token_sale_amt, (sale.hard_cap - sale.owner_allocation));
-            if let Some(cap) = sale.max_cur_alloc_per {
-                if cur.amount + sale_prog.cur_raised > cap {
-                    Err(ContractError::buy(
-                        "Token bought exceed maximum allowed per account",
-                    ))?;
-                }
+        SaleStatus::Cancelled => {
+            Err(ContractError::Cancelled)?;
+        }
+        SaleStatus::Ongoing { round: None } => {
+            Err(ContractError::buy("No round is open at the moment"))?;
+        }
+        SaleStatus::Ongoing { round: Some(_) } => {
+            let (round_idx, round) = sale
+                .active_round(env.block.time.seconds())
+                .ok_or_else(|| ContractError::buy("No round is open at the moment"))?;
+            let now = env.block.time.seconds();
+
+            // lottery sales only admit the drawn winners, up to the lottery allocation
+            if let Some(lottery) = &sale.lottery {
+                lottery
+                    .is_drawn(&sale_prog)
+                    .then_some(())
+                    .ok_or_else(|| ContractError::lottery("Lottery winners are not drawn yet"))?;
+                LOTTERY_WINNER
+                    .has(deps.storage, (id, &buyer))
+                    .then_some(())
+                    .ok_or_else(|| ContractError::lottery("Only lottery winners can participate"))?;
             }
 
-            if let Some(wl_end) = sale.wl_end_time {
-                if wl_end <= env.block.time.seconds() {
-                    PRESALE_WL
-                        .has(deps.storage, (id, &buyer))
-                        .then(|| ())
-                        .ok_or_else(|| {
-                            ContractError::whitelist("Buyer address is not whitelisted")
-                        })?;
+            let cur_total = sale_pers.cur_spent + cur.amount;
+            if round.guaranteed {
+                let weight = TIER_WEIGHT
+                    .may_load(deps.storage, (id, &buyer))?
+                    .ok_or_else(|| ContractError::tier("Address is not registered for the guaranteed round"))?;
+                let cap = sale.tier_cap(&sale_prog, weight);
+                (cur_total <= cap)
+                    .then_some(())
+                    .ok_or(ContractError::AboveMaxContribution(cap))?;
+            }
+            if let Some(cap) = sale.max_contribution(&round) {
+                (cur_total <= cap)
+                    .then_some(())
+                    .ok_or(ContractError::AboveMaxContribution(cap))?;
+            }
+            if let Some(min) = sale.min_cur_alloc_per {
+                (cur_total >= min)
+                    .then_some(())
+                    .ok_or(ContractError::BelowMinContribution(min))?;
+            }
+            if sale_pers.cur_spent.is_zero() {
+                if let Some(max) = sale.max_participants {
+                    (sale_prog.participants < max)
+                        .then_some(())
+                        .ok_or(ContractError::ParticipantLimit(max))?;
                 }
+                sale_prog.participants += 1;
+            }
+
+            // whitelisted rounds only admit buyers in the round allowlist, the merkle root or holding a voucher
+            if round.whitelisted && !PRESALE_WL.has(deps.storage, (id, round_idx, &buyer)) {
+                assert_allowlisted(
+                    deps.branch(),
+                    &env,
+                    &sale,
+                    &buyer,
+                    cur_total,
+                    merkle_proof,
+                    voucher,
+                )?;
             }
 
-            if token_bought_amt + sale_prog.token_sold > sale.token_sale_amt
-                || cur.amount + sale_prog.cur_raised > sale.hard_cap
-            {
-                match allow_partial {
-                    true => {
-                        let pt_token_bought_amt = sale.token_sale_amt - sale_prog.token_sold;
-                        let pt_cur_spent = (sale.hard_cap - sale.owner_allocation) - sale_prog.cur_raised;
+            let (token_bought_amt, cur_spent) = match &sale.sale_type {
+                // bids are kept in price order for the settlement
+                SaleType::BatchAuction { reserve_price } => {
+                    let reserve_price = *reserve_price;
+                    let max_price = max_price.ok_or_else(|| ContractError::buy("Bid max price is required"))?;
+                    (max_price >= reserve_price)
+                        .then_some(())
+                        .ok_or_else(|| ContractError::buy("Bid max price is below the reserve price"))?;
+                    (sale_pers.cur_spent.is_zero() || sale_pers.bid_price == max_price)
+                        .then_some(())
+                        .ok_or_else(|| ContractError::buy("Bid max price cannot be changed"))?;
 
-                        sale_prog.token_sold += pt_token_bought_amt;
-                        sale_prog.cur_raised += pt_cur_spent;
+                    AUCTION_BID.update(deps.storage, (id, max_price.atomics().u128(), &buyer), |bid| {
+                        StdResult::Ok(bid.unwrap_or_default() + cur.amount)
+                    })?;
+                    sale_pers.bid_price = max_price;
+                    sale_prog.cur_raised += cur.amount;
+                    (Uint128::zero(), cur.amount)
+                }
+                // the allocation is only known at the end of the sale
+                SaleType::Overflow => {
+                    sale.commit(&mut sale_prog, cur.amount);
+                    (Uint128::zero(), cur.amount)
+                }
+                _ => {
+                    let (token_bought_amt, cur_spent) = sale.quote(&sale_prog, &round, cur.amount, now)?;
+                    (!token_bought_amt.is_zero())
+                        .then_some(())
+                        .ok_or_else(|| ContractError::buy("Amount too small to buy any token"))?;
 
-                        sale_pers.token_got += pt_token_bought_amt;
-                        sale_pers.cur_spent += pt_cur_spent;
+                    // currency left over once the inventory runs out is only returned on request,
+                    // curve rounding dust always is
+                    if cur_spent < cur.amount {
+                        let is_sold_out = sale_prog.token_sold + token_bought_amt >= sale.token_sale_amt
+                            || sale_prog.cur_raised + cur_spent >= sale.raise_cap();
+                        (allow_partial || !is_sold_out)
+                            .then_some(())
+                            .ok_or_else(|| ContractError::buy("Token bought exceed sale amount"))?;
 
-                        PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
-                        PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&buyer, id), &sale_pers)?;
                         msgs.push(
                             Asset {
-                                info: cur.info,
-                                amount: cur.amount - pt_cur_spent,
+                                info: cur.info.clone(),
+                                amount: cur.amount - cur_spent,
                             }
                             .transfer_msg(&buyer)?,
                         );
                     }
-                    false => Err(ContractError::buy("Token bought exceed sale amount"))?,
-                };
-            } else {
-                sale_prog.token_sold += token_bought_amt;
-                sale_prog.cur_raised += cur.amount;
+                    if let Some(min_tokens_out) = min_tokens_out {
+                        (token_bought_amt >= min_tokens_out)
+                            .then_some(())
+                            .ok_or(ContractError::Slippage(min_tokens_out, token_bought_amt))?;
+                    }
+                    if let SaleType::DutchAuction { .. } = sale.sale_type {
+                        sale_prog.clearing_price = sale.auction_price(now);
+                    }
 
-                sale_pers.token_got += token_bought_amt;
-                sale_pers.cur_spent += cur.amount;
+                    sale_prog.token_sold += token_bought_amt;
+                    sale_prog.cur_raised += cur_spent;
+                    (token_bought_amt, cur_spent)
+                }
+            };
 
-                PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
-                PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&buyer, id), &sale_pers)?;
+            sale_prog.round_raised[round_idx as usize] += cur_spent;
+            sale_pers.token_got += token_bought_amt;
+            sale_pers.cur_spent += cur_spent;
+
+            let referrer = credit_referral(
+                deps.storage,
+                &sale,
+                &mut sale_prog,
+                &buyer,
+                &sale_pers,
+                referral_code,
+                (cur_spent, token_bought_amt),
+            )?;
+            if let Some(referrer) = referrer {
+                attrs.push(("referrer", referrer.to_string()));
             }
+
+            PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+            PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&buyer, id), &sale_pers)?;
         }
     };
 
     Ok(Response::new()
-        .add_messages(msgs))
+        .add_messages(msgs)
+        .add_attribute("action", "participate")
+        .add_attributes(attrs))
 }
 
 pub fn execute_claim(
-    mut deps: DepsMut,
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
     id: u64,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-
     let sale = PRESALE.load(deps.storage, id)?;
     let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let now = env.block.time.seconds();
 
     let mut msgs = vec![];
+    let mut submsgs = vec![];
+    let mut attrs = vec![];
 
-    match sale.status(&sale_prog, env.block.time.seconds()) {
+    match sale.status(&sale_prog, now) {
         SaleStatus::NotStarted => {
             Err(ContractError::NotStarted)?;
         }
-        SaleStatus::Ongoing => {
+        SaleStatus::Ongoing { .. } => {
             Err(ContractError::Ongoing)?;
         }
         SaleStatus::Failed => {
             Err(ContractError::Failed)?;
         }
+        SaleStatus::Cancelled => {
+            Err(ContractError::Cancelled)?;
+        }
         SaleStatus::Ended | SaleStatus::Filled => {
+            (!sale.awaits_settlement(&sale_prog))
+                .then_some(())
+                .ok_or_else(|| ContractError::claim("Auction is not settled yet"))?;
             let token_address = TOKEN_ADDRESS_BY_PRESALE_ID.load(deps.storage, id)?;
-            let presale = PRESALE.load(deps.storage, id)?;
             match info.sender == sale.owner {
                 true => {
-                    // check if excess is sent
-                    (!sale_prog.is_excess_sent)
-                        .then(|| ())
-                        .ok_or_else(|| ContractError::claim("Already claim currency"))?;
-
-                    // update current state
-                    sale_prog.is_excess_sent = true;
-                    // Change to owner click mint first
-                    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+                    // raised currency is settled once, the owner allocation follows its vesting
+                    if !sale_prog.is_excess_sent {
+                        let config = CONFIG.load(deps.storage)?;
+                        sale.settle_raised(&mut sale_prog, now);
+                        let (cur_fee, token_fee) = sale.fees(&sale_prog);
+
+                        // contribution referrals are credited as participants claim, their share is held back
+                        sale_prog.referral_held = sale.referral_reward(
+                            sale.referred_raised(&sale_prog, now),
+                            sale.referral.participant_percentage,
+                        );
+                        sale_prog.referral_reward = sale_prog.referral_held;
+
+                        // the sale referrer is rewarded on top of the per-contribution referrals
+                        if let Some(referrer) = &sale.referrer {
+                            let reward = sale.referral_reward(sale_prog.cur_raised, sale.referral.sale_percentage);
+                            if !reward.is_zero() {
+                                accrue_referral_reward(deps.storage, referrer, &sale.cur_info, reward)?;
+                                sale_prog.referral_reward += reward;
+                                attrs.push(("referrer", referrer.to_string()));
+                            }
+                        }
+
+                        let (fee_payout, owner_payout) = match sale.referral.base {
+                            ReferralBase::PlatformFee => (
+                                cur_fee.checked_sub(sale_prog.referral_reward)?,
+                                sale_prog.cur_raised.checked_sub(cur_fee)?,
+                            ),
+                            ReferralBase::Raised => (
+                                cur_fee,
+                                sale_prog.cur_raised.checked_sub(cur_fee + sale_prog.referral_reward)?,
+                            ),
+                        };
+
+                        // part of the owner share is paired with the token reserve on the DEX
+                        let (cur_liquidity, token_liquidity) = match &sale.liquidity {
+                            Some(liquidity) => {
+                                let (cur_liquidity, token_liquidity) = liquidity.amounts(sale_prog.cur_raised);
+                                if !cur_liquidity.is_zero() {
+                                    let reply_id = REPLY_SEQ.load(deps.storage)? + 1;
+                                    REPLY_SEQ.save(deps.storage, &reply_id)?;
+                                    PENDING_LIQUIDITY.save(deps.storage, reply_id, &id)?;
+
+                                    submsgs.extend(provide_liquidity_msgs(
+                                        &liquidity.pair,
+                                        Asset::cw20(token_address.clone(), token_liquidity),
+                                        Asset::new(sale.cur_info.clone(), cur_liquidity),
+                                        &info.sender,
+                                        reply_id,
+                                    )?);
+                                }
+                                (cur_liquidity, token_liquidity)
+                            }
+                            None => (Uint128::zero(), Uint128::zero()),
+                        };
+                        let owner_payout = owner_payout.checked_sub(cur_liquidity)?;
+
+                        // unsold tokens and the unused fee and liquidity reserves go back to the owner
+                        let token_excess = sale_prog.token_escrowed
+                            - sale_prog.token_sold
+                            - sale.owner_allocation
+                            - token_fee
+                            - token_liquidity;
 
-                    // transfer excess amount of currency
-                    msgs.push(
-                        Asset {
-                            info: AssetInfoBase::Cw20((token_address)),
-                            amount: presale.owner_allocation,
+                        sale_prog.is_excess_sent = true;
+                        sale_prog.cur_fee = cur_fee;
+                        sale_prog.token_fee = token_fee;
+                        sale_prog.token_excess = token_excess;
+                        sale_prog.cur_liquidity = cur_liquidity;
+                        sale_prog.token_liquidity = token_liquidity;
+
+                        // referred participants may all have settled already
+                        msgs.extend(referral_surplus_msg(deps.storage, &sale, &sale_prog)?);
+
+                        if !owner_payout.is_zero() {
+                            msgs.push(
+                                Asset {
+                                    info: sale.cur_info.clone(),
+                                    amount: owner_payout,
+                                }
+                                .transfer_msg(info.sender.clone())?,
+                            );
+                        }
+                        if !fee_payout.is_zero() {
+                            msgs.push(
+                                Asset {
+                                    info: sale.cur_info.clone(),
+                                    amount: fee_payout,
+                                }
+                                .transfer_msg(&config.fee_collector)?,
+                            );
                         }
-                        .transfer_msg(info.sender.clone())?,
-                    );
-                    msgs.push(
-                        Asset {
-                            info: presale.cur_info,
-                            amount: sale_prog.cur_raised,
+                        if !token_fee.is_zero() {
+                            msgs.push(
+                                Asset {
+                                    info: AssetInfoBase::Cw20(token_address.clone()),
+                                    amount: token_fee,
+                                }
+                                .transfer_msg(&config.fee_collector)?,
+                            );
                         }
-                        .transfer_msg(info.sender.clone())?,
-                    );
+                        if !token_excess.is_zero() {
+                            msgs.push(
+                                Asset {
+                                    info: AssetInfoBase::Cw20(token_address.clone()),
+                                    amount: token_excess,
+                                }
+                                .transfer_msg(info.sender.clone())?,
+                            );
+                        }
+
+                        attrs.push(("fee_collector", config.fee_collector.to_string()));
+                        attrs.push(("cur_fee", cur_fee.to_string()));
+                        attrs.push(("token_fee", token_fee.to_string()));
+                        attrs.push(("cur_liquidity", cur_liquidity.to_string()));
+                        attrs.push(("token_liquidity", token_liquidity.to_string()));
+                        attrs.push(("referral_reward", sale_prog.referral_reward.to_string()));
+                    }
+
+                    let claimable = sale.owner_vested(now) - sale_prog.owner_token_claimed;
+                    if !claimable.is_zero() {
+                        sale_prog.owner_token_claimed += claimable;
+
+                        msgs.push(
+                            Asset {
+                                info: AssetInfoBase::Cw20(token_address),
+                                amount: claimable,
+                            }
+                            .transfer_msg(info.sender.clone())?,
+                        );
+                    }
+
+                    (!msgs.is_empty() || !submsgs.is_empty())
+                        .then_some(())
+                        .ok_or_else(|| match sale_prog.owner_token_claimed < sale.owner_allocation {
+                            true => ContractError::claim("No vested owner allocation to claim yet"),
+                            false => ContractError::claim("Already claim currency and token"),
+                        })?;
+
+                    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
                 }
                 false => {
                     // participant
@@ -261,28 +748,63 @@ pub fn execute_claim(
                         .load(deps.storage, (&info.sender, id))
                         .map_err(|_| ContractError::ParticipationNotFound)?;
 
-                    // check if already claimed
-                    (!sale_pers.is_claimed)
-                        .then(|| ())
-                        .ok_or_else(|| ContractError::claim("Already claim token"))?;
+                    // the final allocation is fixed on the first claim, returning unused currency
+                    if !sale_pers.is_settled {
+                        let (token_got, cur_returned) = sale.allocation(&sale_prog, &info.sender, &sale_pers, now);
+                        (!token_got.is_zero() || !matches!(sale.sale_type, SaleType::BatchAuction { .. }))
+                            .then_some(())
+                            .ok_or_else(|| ContractError::claim("Bid was not filled, please `refund` instead"))?;
+                        sale_pers.token_got = token_got;
+                        sale_pers.cur_returned = cur_returned;
+                        sale_pers.is_settled = true;
 
-                    // update current state
-                    sale_pers.is_claimed = true;
-                    PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&info.sender, id), &sale_pers)?;
-
-                    // update total token claimed state
-                    sale_prog.token_claimed += sale_pers.token_got;
-                    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+                        // the referrer earns on what the participant finally paid
+                        msgs.extend(settle_referral(
+                            deps.storage,
+                            &sale,
+                            &mut sale_prog,
+                            &info.sender,
+                            sale_pers.cur_spent,
+                            sale_pers.cur_spent - cur_returned,
+                        )?);
 
-                    // transfer token bought
-                    //change to mint and transfer
-                    msgs.push(
-                        Asset {
-                            info: AssetInfoBase::Cw20((token_address)),
-                            amount: sale_pers.token_got,
+                        if !cur_returned.is_zero() {
+                            msgs.push(
+                                Asset {
+                                    info: sale.cur_info.clone(),
+                                    amount: cur_returned,
+                                }
+                                .transfer_msg(&info.sender)?,
+                            );
+                            attrs.push(("cur_returned", cur_returned.to_string()));
                         }
-                        .transfer_msg(info.sender)?,
-                    )
+                    }
+
+                    // only the vested part which has not been claimed yet is released
+                    let claimable = sale.token_vested(sale_pers.token_got, now) - sale_pers.token_claimed;
+                    if !claimable.is_zero() {
+                        sale_pers.token_claimed += claimable;
+                        sale_prog.token_claimed += claimable;
+
+                        // transfer token bought
+                        msgs.push(
+                            Asset {
+                                info: AssetInfoBase::Cw20(token_address),
+                                amount: claimable,
+                            }
+                            .transfer_msg(&info.sender)?,
+                        );
+                    }
+
+                    (!msgs.is_empty())
+                        .then_some(())
+                        .ok_or_else(|| match sale_pers.token_claimed < sale_pers.token_got {
+                            true => ContractError::claim("No vested token to claim yet"),
+                            false => ContractError::claim("Already claim token"),
+                        })?;
+
+                    PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&info.sender, id), &sale_pers)?;
+                    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
                 }
             };
         }
@@ -290,7 +812,59 @@ pub fn execute_claim(
 
     Ok(Response::new()
         .add_messages(msgs)
-        .add_attribute("action", "claim"))
+        .add_submessages(submsgs)
+        .add_attribute("action", "claim")
+        .add_attributes(attrs))
+}
+
+/// Pays the currency and tokens reserved for the DEX liquidity to the sale
+/// owner when the pair rejected them, the allowances granted to it revoked.
+pub fn reply_provide_liquidity(deps: DepsMut, id: u64, result: SubMsgResult) -> Result<Response, ContractError> {
+    let error = match result {
+        SubMsgResult::Ok(_) => return Ok(Response::new()),
+        SubMsgResult::Err(error) => error,
+    };
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let token_address = TOKEN_ADDRESS_BY_PRESALE_ID.load(deps.storage, id)?;
+    let pair = sale
+        .liquidity
+        .as_ref()
+        .map(|liquidity| liquidity.pair.to_string())
+        .ok_or_else(|| ContractError::custom("Sale provides no liquidity"))?;
+
+    let mut msgs = vec![];
+    for asset in [
+        Asset::cw20(token_address, sale_prog.token_liquidity),
+        Asset::new(sale.cur_info.clone(), sale_prog.cur_liquidity),
+    ]
+    .iter()
+    {
+        if let AssetInfo::Cw20(contract_addr) = &asset.info {
+            msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::DecreaseAllowance {
+                    spender: pair.clone(),
+                    amount: asset.amount,
+                    expires: None,
+                })?,
+                funds: vec![],
+            }));
+        }
+        msgs.push(asset.transfer_msg(&sale.owner)?);
+    }
+
+    // the token reserve is released to the owner like the unused part of it
+    sale_prog.token_excess += sale_prog.token_liquidity;
+    sale_prog.cur_liquidity = Uint128::zero();
+    sale_prog.token_liquidity = Uint128::zero();
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "provide_liquidity_failed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("error", error))
 }
 
 pub fn execute_refund(
@@ -303,27 +877,61 @@ pub fn execute_refund(
     let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
 
     let mut msgs = vec![];
+    let now = env.block.time.seconds();
 
-    match sale.status(&sale_prog, env.block.time.seconds()) {
+    match sale.status(&sale_prog, now) {
         SaleStatus::NotStarted => {
             Err(ContractError::NotStarted)?;
         }
-        SaleStatus::Ongoing => {
+        SaleStatus::Ongoing { .. } => {
             Err(ContractError::Ongoing)?;
         }
         SaleStatus::Ended | SaleStatus::Filled => {
-            Err(ContractError::Ended)?;
+            // bids left out of a settled batch auction are refunded in full
+            let sale_pers = match (&sale.sale_type, info.sender == sale.owner) {
+                (SaleType::BatchAuction { .. }, false) if sale_prog.is_auction_settled => {
+                    PRESALE_PARTICIPANT_BY_PRESALE_ID.may_load(deps.storage, (&info.sender, id))?
+                }
+                _ => None,
+            };
+            let mut sale_pers = match sale_pers {
+                Some(sale_pers) if sale.allocation(&sale_prog, &info.sender, &sale_pers, now).0.is_zero() => sale_pers,
+                _ => Err(ContractError::Ended)?,
+            };
+            (!sale_pers.is_refunded)
+                .then_some(())
+                .ok_or_else(|| ContractError::refund("Already refunded or claimed to participant"))?;
+
+            msgs.push(
+                Asset {
+                    info: sale.cur_info.clone(),
+                    amount: sale_pers.cur_spent,
+                }
+                .transfer_msg(&info.sender)?,
+            );
+
+            msgs.extend(settle_referral(
+                deps.storage,
+                &sale,
+                &mut sale_prog,
+                &info.sender,
+                sale_pers.cur_spent,
+                Uint128::zero(),
+            )?);
+            sale_pers.is_refunded = true;
+            PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&info.sender, id), &sale_pers)?;
+            PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
         }
-        SaleStatus::Failed => match info.sender == sale.owner {
+        SaleStatus::Failed | SaleStatus::Cancelled => match info.sender == sale.owner {
             true => {
                 // owner of the token sale -> claim token back
 
                 // check if already refunded
                 (!sale_prog.is_excess_sent)
-                    .then(|| ())
+                    .then_some(())
                     .ok_or_else(|| ContractError::refund("Already refunded excess token"))?;
 
-                let total_token = sale.token_sale_amt;
+                let total_token = sale_prog.token_escrowed;
 
                 msgs.push(
                     Asset {
@@ -346,20 +954,30 @@ pub fn execute_refund(
                     .map_err(|_| ContractError::ParticipationNotFound)?;
 
                 // check if already refunded
-                (!sale_pers.is_refunded && !sale_pers.is_claimed)
-                    .then(|| ())
+                (!sale_pers.is_refunded && sale_pers.token_claimed.is_zero())
+                    .then_some(())
                     .ok_or_else(|| {
                         ContractError::refund("Already refunded or claimed to participant")
                     })?;
 
                 msgs.push(
                     Asset {
-                        info: sale.cur_info,
-                        amount: sale_pers.cur_spent,
+                        info: sale.cur_info.clone(),
+                        amount: sale_pers.cur_spent - sale_pers.cur_returned,
                     }
                     .transfer_msg(&info.sender)?,
                 );
 
+                settle_referral(
+                    deps.storage,
+                    &sale,
+                    &mut sale_prog,
+                    &info.sender,
+                    sale_pers.cur_spent,
+                    Uint128::zero(),
+                )?;
+                PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
                 // save current sale personal progress state
                 sale_pers.is_refunded = true;
                 PRESALE_PARTICIPANT_BY_PRESALE_ID.save(deps.storage, (&info.sender, id), &sale_pers)?;
@@ -370,4 +988,681 @@ pub fn execute_refund(
     Ok(Response::new()
         .add_messages(msgs)
         .add_attribute("action", "refund"))
-}
\ No newline at end of file
+}
+pub fn execute_update_whitelist(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    round: u32,
+    addresses: Vec<String>,
+    is_add: bool,
+) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+
+    (info.sender == sale.owner)
+        .then_some(())
+        .ok_or(ContractError::OnlySaleOwner)?;
+    sale.rounds
+        .get(round as usize)
+        .is_some_and(|round| round.whitelisted)
+        .then_some(())
+        .ok_or_else(|| ContractError::whitelist("Round does not exist or is not whitelisted"))?;
+
+    for address in &addresses {
+        let address = deps.api.addr_validate(address)?;
+        match is_add {
+            true => PRESALE_WL.save(deps.storage, (id, round, &address), &())?,
+            false => PRESALE_WL.remove(deps.storage, (id, round, &address)),
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute(
+            "action",
+            match is_add {
+                true => "add_to_whitelist",
+                false => "remove_from_whitelist",
+            },
+        )
+        .add_attribute("id", id.to_string())
+        .add_attribute("round", round.to_string())
+        .add_attribute("count", addresses.len().to_string()))
+}
+
+/// Grants a buyer missing from `PRESALE_WL` access through a merkle proof or a
+/// signed voucher, either one capping the buyer's total spent to `cur_total`.
+fn assert_allowlisted(
+    deps: DepsMut,
+    env: &Env,
+    sale: &Sale,
+    buyer: &Addr,
+    cur_total: Uint128,
+    merkle_proof: Option<MerkleProof>,
+    voucher: Option<SignedVoucher>,
+) -> Result<(), ContractError> {
+    let max_amount = match (merkle_proof, voucher) {
+        (Some(proof), _) => {
+            sale.verify_merkle_proof(buyer, &proof)?
+                .then_some(())
+                .ok_or_else(|| ContractError::whitelist("Invalid merkle proof"))?;
+            proof.max_allocation
+        }
+        (None, Some(SignedVoucher { voucher, signature })) => {
+            let signer = sale
+                .voucher_signer
+                .as_ref()
+                .ok_or_else(|| ContractError::whitelist("Sale does not accept vouchers"))?;
+
+            (voucher.sale_id == sale.id && voucher.buyer == buyer.as_str())
+                .then_some(())
+                .ok_or_else(|| ContractError::whitelist("Voucher issued for another sale or buyer"))?;
+            (voucher.expiry >= env.block.time.seconds())
+                .then_some(())
+                .ok_or_else(|| ContractError::whitelist("Voucher expired"))?;
+            (!VOUCHER_NONCE.has(deps.storage, (sale.id, voucher.nonce)))
+                .then_some(())
+                .ok_or_else(|| ContractError::whitelist("Voucher nonce already used"))?;
+
+            let hash = Sha256::digest(to_json_vec(&voucher)?);
+            deps.api
+                .secp256k1_verify(&hash, &signature, signer)
+                .unwrap_or(false)
+                .then_some(())
+                .ok_or_else(|| ContractError::whitelist("Invalid voucher signature"))?;
+
+            VOUCHER_NONCE.save(deps.storage, (sale.id, voucher.nonce), &())?;
+            voucher.max_amount
+        }
+        (None, None) => Err(ContractError::whitelist("Buyer address is not whitelisted"))?,
+    };
+
+    (cur_total <= max_amount)
+        .then_some(())
+        .ok_or_else(|| ContractError::whitelist("Amount exceeds allowlist allocation"))
+}
+
+pub fn execute_update_merkle_root(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    merkle_root: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut sale = PRESALE.load(deps.storage, id)?;
+    let sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+
+    (info.sender == sale.owner)
+        .then_some(())
+        .ok_or(ContractError::OnlySaleOwner)?;
+    (sale.status(&sale_prog, env.block.time.seconds()) == SaleStatus::NotStarted)
+        .then_some(())
+        .ok_or_else(|| ContractError::whitelist("Merkle root can only be updated before start"))?;
+
+    if let Some(root) = &merkle_root {
+        assert_valid_merkle_root(root)?;
+    }
+    sale.merkle_root = merkle_root.clone();
+    PRESALE.save(deps.storage, id, &sale)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_merkle_root")
+        .add_attribute("id", id.to_string())
+        .add_attribute("merkle_root", merkle_root.unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn execute_cancel_sale(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    reason: String,
+) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let status = sale.status(&sale_prog, env.block.time.seconds());
+
+    // owner may only abort before start, admin at any time
+    let is_admin = ADMIN.is_admin(deps.as_ref(), &info.sender)?;
+    match (is_admin, info.sender == sale.owner) {
+        (true, _) => {}
+        (false, true) => (status == SaleStatus::NotStarted)
+            .then_some(())
+            .ok_or_else(|| ContractError::cancel("Sale owner can only cancel before start"))?,
+        (false, false) => Err(ContractError::Unauthorized)?,
+    }
+
+    // once a sale has an outcome, claims may already have paid out of it
+    match status {
+        SaleStatus::NotStarted | SaleStatus::Ongoing { .. } => {}
+        SaleStatus::Cancelled => Err(ContractError::cancel("Sale already cancelled"))?,
+        _ => Err(ContractError::cancel("Sale already ended"))?,
+    }
+    (!sale_prog.is_excess_sent && sale_prog.token_claimed.is_zero())
+        .then_some(())
+        .ok_or_else(|| ContractError::cancel("Sale already settled"))?;
+
+    sale_prog.is_cancelled = true;
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_sale")
+        .add_attribute("id", id.to_string())
+        .add_attribute("reason", reason))
+}
+
+pub fn execute_register_referral_code(
+    deps: DepsMut,
+    info: MessageInfo,
+    code: String,
+) -> Result<Response, ContractError> {
+    (!code.is_empty() && code.len() <= MAX_REFERRAL_CODE_LEN)
+        .then_some(())
+        .ok_or_else(|| ContractError::referral("Invalid referral code length"))?;
+    (!REFERRAL_CODE.has(deps.storage, &code))
+        .then_some(())
+        .ok_or_else(|| ContractError::referral("Referral code already taken"))?;
+
+    REFERRAL_CODE.save(deps.storage, &code, &info.sender)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_referral_code")
+        .add_attribute("code", code)
+        .add_attribute("referrer", info.sender))
+}
+
+pub fn execute_claim_referral_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let rewards = REFERRAL_REWARD
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs = vec![];
+    for (key, mut reward) in rewards {
+        let amount = reward.accrued - reward.claimed;
+        if amount.is_zero() {
+            continue;
+        }
+
+        msgs.push(
+            Asset {
+                info: reward.info.clone(),
+                amount,
+            }
+            .transfer_msg(&info.sender)?,
+        );
+
+        reward.claimed = reward.accrued;
+        REFERRAL_REWARD.save(deps.storage, (&info.sender, &key), &reward)?;
+    }
+
+    (!msgs.is_empty())
+        .then_some(())
+        .ok_or_else(|| ContractError::referral("No referral reward to claim"))?;
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "claim_referral_rewards")
+        .add_attribute("referrer", info.sender))
+}
+
+/// Attributes a participation to its referrer, set by the first contribution
+/// carrying a referral code, and records the reward pending on the commitment.
+fn credit_referral(
+    storage: &mut dyn Storage,
+    sale: &Sale,
+    sale_prog: &mut SaleProgress,
+    buyer: &Addr,
+    sale_pers: &SaleProgressPersonal,
+    code: Option<String>,
+    (cur_spent, token_bought): (Uint128, Uint128),
+) -> Result<Option<Addr>, ContractError> {
+    let current = REFERRAL_OF.may_load(storage, (sale.id, buyer))?;
+    let (referrer, cur_added, token_added) = match (current, code) {
+        (None, None) => return Ok(None),
+        (Some(referrer), None) => (referrer, cur_spent, token_bought),
+        (current, Some(code)) => {
+            let referrer = REFERRAL_CODE
+                .may_load(storage, &code)?
+                .ok_or_else(|| ContractError::referral("Referral code not found"))?;
+            (referrer != *buyer)
+                .then_some(())
+                .ok_or_else(|| ContractError::referral("Cannot use own referral code"))?;
+            // the owner would take a cut of their own raise
+            (referrer != sale.owner)
+                .then_some(())
+                .ok_or_else(|| ContractError::referral("Cannot use the sale owner's referral code"))?;
+
+            match current {
+                Some(current) => {
+                    (current == referrer)
+                        .then_some(())
+                        .ok_or_else(|| ContractError::referral("Referral code cannot change"))?;
+                    (referrer, cur_spent, token_bought)
+                }
+                // earlier contributions follow the participation to its referrer
+                None => {
+                    REFERRAL_OF.save(storage, (sale.id, buyer), &referrer)?;
+                    sale_prog.referred += 1;
+                    (referrer, sale_pers.cur_spent, sale_pers.token_got)
+                }
+            }
+        }
+    };
+    sale_prog.referred_spent += cur_added;
+    sale_prog.referred_token += token_added;
+
+    let percentage = sale.referral.participant_percentage;
+    let reward = sale.referral_reward(sale_pers.cur_spent, percentage)
+        - sale.referral_reward(sale_pers.cur_spent - cur_added, percentage);
+    if !reward.is_zero() {
+        REFERRAL_PENDING.update(storage, (&referrer, sale.id), |pending| -> StdResult<_> {
+            Ok(pending.unwrap_or_default() + reward)
+        })?;
+    }
+
+    Ok(Some(referrer))
+}
+
+/// Replaces the reward pending on the `cur_spent` commitment of a referred
+/// participant with the reward on `cur_paid`, what they finally paid. The
+/// last referred participant to settle releases the unused held back rewards.
+fn settle_referral(
+    storage: &mut dyn Storage,
+    sale: &Sale,
+    sale_prog: &mut SaleProgress,
+    participant: &Addr,
+    cur_spent: Uint128,
+    cur_paid: Uint128,
+) -> StdResult<Option<CosmosMsg>> {
+    let referrer = match REFERRAL_OF.may_load(storage, (sale.id, participant))? {
+        Some(referrer) => referrer,
+        None => return Ok(None),
+    };
+
+    let percentage = sale.referral.participant_percentage;
+    let pending = REFERRAL_PENDING.may_load(storage, (&referrer, sale.id))?.unwrap_or_default()
+        - sale.referral_reward(cur_spent, percentage);
+    match pending.is_zero() {
+        true => REFERRAL_PENDING.remove(storage, (&referrer, sale.id)),
+        false => REFERRAL_PENDING.save(storage, (&referrer, sale.id), &pending)?,
+    }
+
+    let reward = sale.referral_reward(cur_paid, percentage);
+    if !reward.is_zero() {
+        accrue_referral_reward(storage, &referrer, &sale.cur_info, reward)?;
+    }
+    sale_prog.referred_settled += 1;
+    sale_prog.referral_paid += reward;
+
+    referral_surplus_msg(storage, sale, sale_prog)
+}
+
+/// Pays the unused contribution referral rewards of a settled sale back to
+/// the fee collector or the owner, whichever share they were carved out of.
+fn referral_surplus_msg(storage: &dyn Storage, sale: &Sale, sale_prog: &SaleProgress) -> StdResult<Option<CosmosMsg>> {
+    let surplus = sale.referral_surplus(sale_prog);
+    if surplus.is_zero() {
+        return Ok(None);
+    }
+
+    let recipient = match sale.referral.base {
+        ReferralBase::PlatformFee => CONFIG.load(storage)?.fee_collector,
+        ReferralBase::Raised => sale.owner.clone(),
+    };
+    Asset::new(sale.cur_info.clone(), surplus).transfer_msg(recipient).map(Some)
+}
+
+fn accrue_referral_reward(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    cur_info: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let key = cur_info.to_string();
+    let mut reward = REFERRAL_REWARD
+        .may_load(storage, (referrer, &key))?
+        .unwrap_or_else(|| ReferralReward {
+            info: cur_info.clone(),
+            accrued: Uint128::zero(),
+            claimed: Uint128::zero(),
+        });
+    reward.accrued += amount;
+
+    REFERRAL_REWARD.save(storage, (referrer, &key), &reward)
+}
+
+/// Walks the bids of an ended batch auction from the highest max price, until
+/// the inventory is covered and the clearing price is known.
+pub fn execute_settle_auction(deps: DepsMut, env: Env, id: u64) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+
+    let reserve_price = match sale.sale_type {
+        SaleType::BatchAuction { reserve_price } => reserve_price,
+        _ => Err(ContractError::custom("Sale is not a batch auction"))?,
+    };
+    (sale.status(&sale_prog, env.block.time.seconds()) == SaleStatus::Ended)
+        .then_some(())
+        .ok_or_else(|| ContractError::custom("Only ended auctions can be settled"))?;
+    (!sale_prog.is_auction_settled)
+        .then_some(())
+        .ok_or_else(|| ContractError::custom("Auction already settled"))?;
+
+    let bids = AUCTION_BID
+        .sub_prefix(id)
+        .range(
+            deps.storage,
+            None,
+            sale_prog
+                .bid_cursor
+                .as_ref()
+                .map(|(price, addr)| Bound::exclusive((price.u128(), addr))),
+            Order::Descending,
+        )
+        .take(MAX_BID_SETTLE)
+        .collect::<StdResult<Vec<_>>>()?;
+    let is_last_page = bids.len() < MAX_BID_SETTLE;
+    let supply = sale.token_sale_amt;
+    let committed = sale_prog.cur_raised;
+
+    let mut processed = 0;
+    for ((price, bidder), amount) in bids {
+        processed += 1;
+        let price = Decimal::raw(price);
+
+        if sale_prog.bid_total + amount >= supply.mul_ceil(price) {
+            let accepted = match sale_prog.bid_total >= supply.mul_ceil(price) {
+                // bids above already cover the supply at a price between theirs and this one
+                true => {
+                    let clearing_price = Decimal::from_ratio(sale_prog.bid_total, supply);
+                    sale_prog.clearing_price = match supply.mul_floor(clearing_price) < sale_prog.bid_total {
+                        true => clearing_price + Decimal::raw(1),
+                        false => clearing_price,
+                    };
+                    Uint128::zero()
+                }
+                // this bid is partly filled at its own price
+                false => {
+                    let accepted = supply.mul_floor(price) - sale_prog.bid_total;
+                    sale_prog.clearing_price = price;
+                    if !accepted.is_zero() {
+                        if REFERRAL_OF.has(deps.storage, (id, &bidder)) {
+                            sale_prog.referred_filled += accepted;
+                        }
+                        sale_prog.marginal_bid = Some((bidder, accepted));
+                    }
+                    accepted
+                }
+            };
+
+            sale_prog.token_sold = supply;
+            sale_prog.cur_raised = sale_prog.bid_total + accepted;
+            sale_prog.is_auction_settled = true;
+            break;
+        }
+
+        if REFERRAL_OF.has(deps.storage, (id, &bidder)) {
+            sale_prog.referred_filled += amount;
+        }
+        sale_prog.bid_total += amount;
+        sale_prog.bid_cursor = Some((price.atomics(), bidder));
+    }
+
+    // every bid is filled when they do not cover the supply, at the lowest max price
+    if !sale_prog.is_auction_settled && is_last_page {
+        sale_prog.clearing_price = match &sale_prog.bid_cursor {
+            Some((price, _)) => Decimal::raw(price.u128()),
+            None => reserve_price,
+        };
+        sale_prog.token_sold = sale_prog
+            .bid_total
+            .multiply_ratio(sale_prog.clearing_price.denominator(), sale_prog.clearing_price.numerator());
+        sale_prog.cur_raised = sale_prog.bid_total;
+        sale_prog.is_auction_settled = true;
+    }
+    if sale_prog.is_auction_settled {
+        sale_prog.cur_excess = committed - sale_prog.cur_raised;
+    }
+
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "settle_auction")
+        .add_attribute("id", id.to_string())
+        .add_attribute("processed", processed.to_string())
+        .add_attribute("is_settled", sale_prog.is_auction_settled.to_string())
+        .add_attribute("clearing_price", sale_prog.clearing_price.to_string()))
+}
+
+fn load_lottery(sale: &Sale) -> Result<&Lottery, ContractError> {
+    sale.lottery
+        .as_ref()
+        .ok_or_else(|| ContractError::lottery("Sale has no lottery"))
+}
+
+/// Registers a lottery ticket for the sender, allowlisted senders only when the
+/// first round is whitelisted.
+pub fn execute_register_ticket(deps: DepsMut, env: Env, info: MessageInfo, id: u64) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let lottery = load_lottery(&sale)?;
+
+    (!sale_prog.is_cancelled)
+        .then_some(())
+        .ok_or(ContractError::Cancelled)?;
+    (env.block.time.seconds() <= lottery.registration_end)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Registration is closed"))?;
+    (sale.owner != info.sender)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Sale owner cannot register"))?;
+    // the operator knows the seed and could grind the tickets hash with their own tickets
+    (lottery.operator != info.sender)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Lottery operator cannot register"))?;
+    if sale.rounds.first().is_some_and(|round| round.whitelisted) {
+        PRESALE_WL
+            .has(deps.storage, (id, 0, &info.sender))
+            .then_some(())
+            .ok_or_else(|| ContractError::whitelist("Address is not whitelisted"))?;
+    }
+    (!LOTTERY_ENTRANT.has(deps.storage, (id, &info.sender)))
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Ticket already registered"))?;
+
+    LOTTERY_ENTRANT.save(deps.storage, (id, &info.sender), &())?;
+    LOTTERY_TICKET.save(deps.storage, (id, sale_prog.tickets), &info.sender)?;
+    let tickets_hash: [u8; 32] = Sha256::new()
+        .chain_update(sale_prog.tickets_hash.as_slice())
+        .chain_update(info.sender.as_bytes())
+        .finalize()
+        .into();
+    sale_prog.tickets_hash = Binary::from(tickets_hash);
+    sale_prog.tickets += 1;
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_ticket")
+        .add_attribute("id", id.to_string())
+        .add_attribute("ticket", (sale_prog.tickets - 1).to_string()))
+}
+
+/// Stores the operator commitment, the hex encoded sha256 of the seed, before
+/// the registration closes.
+pub fn execute_commit_seed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    commitment: String,
+) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let lottery = load_lottery(&sale)?;
+
+    (info.sender == lottery.operator)
+        .then_some(())
+        .ok_or(ContractError::Unauthorized)?;
+    (env.block.time.seconds() <= lottery.registration_end)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Seed must be committed before the registration closes"))?;
+    sale_prog
+        .seed_commitment
+        .is_none()
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Seed already committed"))?;
+    let mut commitment_buf = [0u8; 32];
+    hex::decode_to_slice(&commitment, &mut commitment_buf)
+        .map_err(|_| ContractError::lottery("Commitment must be a hex encoded sha256 hash"))?;
+
+    sale_prog.seed_commitment = Some(commitment.to_lowercase());
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "commit_seed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("commitment", commitment))
+}
+
+/// Reveals the committed seed between the registration end and the sale start,
+/// mixing it with the tickets hash, fixed when the registration closes, and the
+/// reveal block height and time, unknown when the seed is committed.
+///
+/// The operator is trusted not to register tickets through other addresses:
+/// knowing the seed, they could grind the tickets hash with last minute entries.
+/// Delaying the reveal within the deadline only lets them pick among a few blocks.
+pub fn execute_reveal_seed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    seed: String,
+) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let lottery = load_lottery(&sale)?;
+
+    (info.sender == lottery.operator)
+        .then_some(())
+        .ok_or(ContractError::Unauthorized)?;
+    (env.block.time.seconds() > lottery.registration_end)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Registration is still open"))?;
+    (env.block.time.seconds() < sale.start)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Reveal deadline has passed"))?;
+    (sale_prog.seed.is_none())
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Seed already revealed"))?;
+    let commitment = sale_prog
+        .seed_commitment
+        .as_ref()
+        .ok_or_else(|| ContractError::lottery("No seed was committed"))?;
+    (&hex::encode(Sha256::digest(seed.as_bytes())) == commitment)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("Seed does not match the commitment"))?;
+
+    let seed: [u8; 32] = Sha256::new()
+        .chain_update(seed.as_bytes())
+        .chain_update(sale_prog.tickets_hash.as_slice())
+        .chain_update(env.block.height.to_be_bytes())
+        .chain_update(env.block.time.nanos().to_be_bytes())
+        .finalize()
+        .into();
+    sale_prog.seed = Some(Binary::from(seed));
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "reveal_seed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("seed", Binary::from(seed).to_base64()))
+}
+
+/// Draws the next lottery winners, a partial Fisher-Yates shuffle of the tickets
+/// driven by the revealed seed. Every ticket wins when there are not more
+/// tickets than winners.
+pub fn execute_draw_winners(deps: DepsMut, id: u64) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let lottery = load_lottery(&sale)?;
+
+    let seed = sale_prog
+        .seed
+        .clone()
+        .ok_or_else(|| ContractError::lottery("Seed is not revealed yet"))?;
+    let winners = lottery.winners.min(sale_prog.tickets);
+    (sale_prog.tickets_drawn < winners)
+        .then_some(())
+        .ok_or_else(|| ContractError::lottery("All winners are drawn"))?;
+
+    let draw_end = winners.min(sale_prog.tickets_drawn + MAX_WINNER_DRAW);
+    for idx in sale_prog.tickets_drawn..draw_end {
+        let swap_idx = idx + (Lottery::ticket_roll(&seed, idx) % u64::from(sale_prog.tickets - idx)) as u32;
+        let winner = LOTTERY_TICKET.load(deps.storage, (id, swap_idx))?;
+        if swap_idx != idx {
+            let ticket = LOTTERY_TICKET.load(deps.storage, (id, idx))?;
+            LOTTERY_TICKET.save(deps.storage, (id, swap_idx), &ticket)?;
+            LOTTERY_TICKET.save(deps.storage, (id, idx), &winner)?;
+        }
+        LOTTERY_WINNER.save(deps.storage, (id, &winner), &())?;
+    }
+    sale_prog.tickets_drawn = draw_end;
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "draw_winners")
+        .add_attribute("id", id.to_string())
+        .add_attribute("drawn", sale_prog.tickets_drawn.to_string())
+        .add_attribute("is_drawn", (sale_prog.tickets_drawn == winners).to_string()))
+}
+
+/// Registers the sender for the guaranteed rounds of a sale with the tier
+/// weight of its stake before the current block, until the first guaranteed round starts.
+pub fn execute_register_tier(deps: DepsMut, env: Env, info: MessageInfo, id: u64) -> Result<Response, ContractError> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let mut sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let config = TIER_CONFIG
+        .may_load(deps.storage)?
+        .ok_or_else(|| ContractError::tier("Tiers are not configured"))?;
+
+    let guaranteed_start = sale
+        .guaranteed_start()
+        .ok_or_else(|| ContractError::tier("Sale has no guaranteed round"))?;
+    (!sale_prog.is_cancelled)
+        .then_some(())
+        .ok_or(ContractError::Cancelled)?;
+    (env.block.time.seconds() < guaranteed_start)
+        .then_some(())
+        .ok_or_else(|| ContractError::tier("Registration is closed"))?;
+    (sale.owner != info.sender)
+        .then_some(())
+        .ok_or_else(|| ContractError::tier("Sale owner cannot register"))?;
+    (!TIER_WEIGHT.has(deps.storage, (id, &info.sender)))
+        .then_some(())
+        .ok_or_else(|| ContractError::tier("Already registered"))?;
+
+    let staked: StakedResponse = deps.querier.query_wasm_smart(
+        &config.staking_contract,
+        &StakingQueryMsg::StakedAt {
+            address: info.sender.to_string(),
+            height_or_time: Some(HeightOrTime::Height(env.block.height)),
+        },
+    )?;
+    let weight = config.weight(staked.amount);
+    (weight > 0)
+        .then_some(())
+        .ok_or_else(|| ContractError::tier(format!("Staked amount {} is below the lowest tier", staked.amount)))?;
+
+    TIER_WEIGHT.save(deps.storage, (id, &info.sender), &weight)?;
+    sale_prog.tier_weight_total += u64::from(weight);
+    PRESALE_PROGRESS.save(deps.storage, id, &sale_prog)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_tier")
+        .add_attribute("id", id.to_string())
+        .add_attribute("staked", staked.amount)
+        .add_attribute("weight", weight.to_string()))
+}
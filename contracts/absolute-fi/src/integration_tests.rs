@@ -0,0 +1,3504 @@
+use cosmwasm_std::{coin, coins, to_json_binary, Addr, Binary, Decimal, Empty, Event, Uint128, WasmMsg};
+use cw20::{AllowanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, EmbeddedLogo, Logo};
+use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw_asset::{Asset, AssetInfoUnchecked};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+use crate::msg::{AllocationResponse, IsWinnerResponse, TierRegistrationResponse, TiersResponse, SimulateBuyResponse, ContributionLimitResponse, CurrentPriceResponse, CurrencyWhitelistResponse, EscrowResponse, ValidateSellParamResponse, ExecuteMsg, InstantiateMsg, PendingReferralReward, QueryMsg, ReceiveMsg, ReferralCodeResponse, ReferralRewardsResponse, SaleResponse, VestingResponse, WhitelistResponse};
+use crate::ContractError;
+use crate::state::{BondingCurve, LiquidityParam, LotteryParam, Tier, TierConfig, PriceDecay, ReferralBase, ReferralConfig, ReferralReward, Round, SaleType, SaleProgressPersonal, SellParam, VestingSchedule};
+
+const ADMIN: &str = "admin";
+const OWNER: &str = "owner";
+const BUYER: &str = "buyer";
+const OTHER: &str = "other";
+const COLLECTOR: &str = "collector";
+const REFERRER: &str = "referrer";
+const DENOM: &str = "uusd";
+
+fn launchpad_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(
+        ContractWrapper::new(
+            crate::contract::execute,
+            crate::contract::instantiate,
+            crate::contract::query,
+        )
+        .with_reply(crate::contract::reply),
+    )
+}
+
+fn round(start: u64, end: u64, whitelisted: bool) -> Round {
+    Round {
+        start,
+        end,
+        whitelisted,
+        max_cur_alloc_per: None,
+        price: None,
+        guaranteed: false,
+    }
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn staking_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        absolute_fi_staking::contract::execute,
+        absolute_fi_staking::contract::instantiate,
+        absolute_fi_staking::contract::query,
+    ))
+}
+
+/// Pair pulling the provided cw20 assets through their allowance and checking
+/// the native ones were sent along.
+mod mock_pair {
+    use cosmwasm_std::{
+        to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult, WasmMsg,
+    };
+    use cw20::Cw20ExecuteMsg;
+    use cw_multi_test::{Contract, ContractWrapper};
+
+    use crate::msg::{PairAssetInfo, PairExecuteMsg};
+
+    fn instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    fn execute(_: DepsMut, env: Env, info: MessageInfo, msg: PairExecuteMsg) -> StdResult<Response> {
+        let PairExecuteMsg::ProvideLiquidity { assets, .. } = msg;
+        let mut res = Response::new();
+        for asset in assets {
+            let amount = asset.amount;
+            match asset.info {
+                PairAssetInfo::Token { contract_addr } => {
+                    res = res.add_message(WasmMsg::Execute {
+                        contract_addr,
+                        msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                            owner: info.sender.to_string(),
+                            recipient: env.contract.address.to_string(),
+                            amount,
+                        })?,
+                        funds: vec![],
+                    })
+                }
+                PairAssetInfo::NativeToken { denom } => {
+                    info.funds
+                        .iter()
+                        .any(|coin| coin.denom == denom && coin.amount == amount)
+                        .then_some(())
+                        .ok_or_else(|| StdError::generic_err("Native asset not sent"))?;
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn query(_: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+        to_json_binary(&Empty {})
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+/// Staking contract answering `StakedAt` with stakes set through `SetStake`,
+/// regardless of the snapshot point.
+mod mock_staking {
+    use absolute_fi_staking::msg::{QueryMsg as StakingQueryMsg, StakedResponse};
+    use cosmwasm_std::{
+        to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult, Uint128,
+    };
+    use cw_multi_test::{Contract, ContractWrapper};
+    use cw_storage_plus::Map;
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+
+    const STAKE: Map<&str, Uint128> = Map::new("stake");
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ExecuteMsg {
+        SetStake { address: String, amount: Uint128 },
+    }
+
+    fn instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+        Ok(Response::new())
+    }
+
+    fn execute(deps: DepsMut, _: Env, _: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+        let ExecuteMsg::SetStake { address, amount } = msg;
+        STAKE.save(deps.storage, &address, &amount)?;
+        Ok(Response::new())
+    }
+
+    fn query(deps: Deps, _: Env, msg: StakingQueryMsg) -> StdResult<Binary> {
+        match msg {
+            StakingQueryMsg::StakedAt { address, .. } => to_json_binary(&StakedResponse {
+                amount: STAKE.may_load(deps.storage, &address)?.unwrap_or_default(),
+            }),
+            _ => Err(StdError::generic_err("unsupported query")),
+        }
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+fn sale_id(res: &AppResponse) -> u64 {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "id")
+        .unwrap()
+        .value
+        .parse()
+        .unwrap()
+}
+
+struct Suite {
+    app: App,
+    launchpad: Addr,
+    cw20_code_id: u64,
+}
+
+impl Suite {
+    fn new() -> Self {
+        Self::with_config(
+            0,
+            0,
+            ReferralConfig {
+                sale_percentage: Uint128::zero(),
+                participant_percentage: Uint128::zero(),
+                base: ReferralBase::PlatformFee,
+            },
+        )
+    }
+
+    fn with_config(fee_percentage: u128, token_fee_percentage: u128, referral: ReferralConfig) -> Self {
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(BUYER), vec![coin(10_000, DENOM), coin(500, "uluna")])
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(OTHER), coins(10_000, DENOM))
+                .unwrap();
+        });
+
+        let cw20_code_id = app.store_code(cw20_contract());
+        let launchpad_code_id = app.store_code(launchpad_contract());
+        let launchpad = app
+            .instantiate_contract(
+                launchpad_code_id,
+                Addr::unchecked(ADMIN),
+                &InstantiateMsg {
+                    min_token_sale_amt: Uint128::zero(),
+                    token_code_id: cw20_code_id,
+                    fee_percentage: Uint128::new(fee_percentage),
+                    token_fee_percentage: Uint128::new(token_fee_percentage),
+                    fee_collector: COLLECTOR.to_string(),
+                    referral,
+                },
+                &[],
+                "launchpad",
+                None,
+            )
+            .unwrap();
+
+        let mut suite = Self {
+            app,
+            launchpad,
+            cw20_code_id,
+        };
+        suite.add_currency(AssetInfoUnchecked::native(DENOM), 0);
+        suite
+    }
+
+    fn add_currency(&mut self, info: AssetInfoUnchecked, min_soft_cap: u128) {
+        self.app
+            .execute_contract(
+                Addr::unchecked(ADMIN),
+                self.launchpad.clone(),
+                &ExecuteMsg::AddCurrency {
+                    info,
+                    min_soft_cap: Uint128::new(min_soft_cap),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    fn now(&self) -> u64 {
+        self.app.block_info().time.seconds()
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        self.app.update_block(|block| block.time = block.time.plus_seconds(seconds));
+    }
+
+    fn create_currency(&mut self, holder: &str, amount: u128) -> Addr {
+        let currency = self
+            .app
+            .instantiate_contract(
+                self.cw20_code_id,
+                Addr::unchecked(ADMIN),
+                &Cw20InstantiateMsg {
+                    name: "Currency".to_string(),
+                    symbol: "CUR".to_string(),
+                    decimals: 6,
+                    initial_balances: vec![Cw20Coin {
+                        address: holder.to_string(),
+                        amount: Uint128::new(amount),
+                    }],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "currency",
+                None,
+            )
+            .unwrap();
+        self.add_currency(AssetInfoUnchecked::cw20(currency.as_str()), 0);
+        currency
+    }
+
+    fn sell_param(&self, cur_info: AssetInfoUnchecked) -> SellParam {
+        let now = self.now();
+        SellParam {
+            referrer: None,
+            start: now + 10,
+            end: now + 1_000,
+            token_sale_amt: Uint128::new(2_000),
+            cur_info,
+            sale_type: SaleType::FixedPrice,
+            soft_cap: Uint128::new(100),
+            hard_cap: Uint128::new(1_000),
+            max_cur_alloc_per: None,
+            min_cur_alloc_per: None,
+            min_participants: None,
+            max_participants: None,
+            owner_allocation: Uint128::zero(),
+            token_name: "Sale Token".to_string(),
+            token_symbol: "SALE".to_string(),
+            token_decimals: 6,
+            token_project: "Sale".to_string(),
+            token_description: "Sale token".to_string(),
+            token_marketing: None,
+            token_logo: None,
+            rounds: vec![],
+            merkle_root: None,
+            voucher_signer: None,
+            vesting: None,
+            owner_vesting: None,
+            lottery: None,
+            liquidity: None,
+        }
+    }
+
+    fn create_presale(&mut self, cur_info: AssetInfoUnchecked) -> u64 {
+        let param = self.sell_param(cur_info);
+        let res = self
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                self.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap();
+
+        self.advance(10);
+        sale_id(&res)
+    }
+
+    fn participate_native(
+        &mut self,
+        id: u64,
+        amount: u128,
+        funds: &[cosmwasm_std::Coin],
+        allow_partial: bool,
+    ) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(BUYER),
+            self.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::native(DENOM, amount),
+                allow_partial,
+                referral_code: None,
+                merkle_proof: None,
+                voucher: None,
+                min_tokens_out: None,
+                max_price: None,
+            },
+            funds,
+        )
+    }
+
+    fn participate_from(&mut self, sender: &str, id: u64, amount: u128) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::native(DENOM, amount),
+                allow_partial: false,
+                referral_code: None,
+                merkle_proof: None,
+                voucher: None,
+                min_tokens_out: None,
+                max_price: None,
+            },
+            &coins(amount, DENOM),
+        )
+    }
+
+    fn bid_from(&mut self, sender: &str, id: u64, amount: u128, max_price: Decimal) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::native(DENOM, amount),
+                allow_partial: false,
+                referral_code: None,
+                merkle_proof: None,
+                voucher: None,
+                min_tokens_out: None,
+                max_price: Some(max_price),
+            },
+            &coins(amount, DENOM),
+        )
+    }
+
+    fn participate_referred(
+        &mut self,
+        sender: &str,
+        id: u64,
+        amount: u128,
+        max_price: Option<Decimal>,
+        code: &str,
+    ) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::native(DENOM, amount),
+                allow_partial: false,
+                referral_code: Some(code.to_string()),
+                merkle_proof: None,
+                voucher: None,
+                min_tokens_out: None,
+                max_price,
+            },
+            &coins(amount, DENOM),
+        )
+    }
+
+    fn referral_rewards(&self, address: &str) -> ReferralRewardsResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.launchpad,
+                &QueryMsg::ReferralRewards {
+                    address: Addr::unchecked(address),
+                },
+            )
+            .unwrap()
+    }
+
+    fn progress(&self, id: u64, address: &str) -> SaleProgressPersonal {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.launchpad,
+                &QueryMsg::Progress {
+                    id,
+                    address: Addr::unchecked(address),
+                },
+            )
+            .unwrap()
+    }
+
+    fn native_balance(&self, address: &Addr) -> u128 {
+        self.app.wrap().query_balance(address, DENOM).unwrap().amount.u128()
+    }
+
+    fn cw20_balance(&self, token: &Addr, address: &Addr) -> u128 {
+        let res: cw20::BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                token,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+}
+
+#[test]
+fn participate_with_native_funds() {
+    let mut suite = Suite::new();
+    let id = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+    let buyer = Addr::unchecked(BUYER);
+
+    suite.participate_native(id, 100, &coins(100, DENOM), false).unwrap();
+
+    assert_eq!(suite.native_balance(&buyer), 9_900);
+    assert_eq!(suite.native_balance(&suite.launchpad), 100);
+
+    let progress = suite.progress(id, BUYER);
+    assert_eq!(progress.cur_spent, Uint128::new(100));
+    assert_eq!(progress.token_got, Uint128::new(200));
+}
+
+#[test]
+fn participate_rejects_unfunded_or_stray_funds() {
+    let mut suite = Suite::new();
+    let id = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+
+    // nothing attached
+    suite.participate_native(id, 100, &[], false).unwrap_err();
+    // less than declared
+    suite.participate_native(id, 100, &coins(50, DENOM), false).unwrap_err();
+    // stray denom next to the currency
+    suite
+        .participate_native(id, 100, &[coin(100, DENOM), coin(100, "uluna")], false)
+        .unwrap_err();
+
+    assert_eq!(suite.native_balance(&Addr::unchecked(BUYER)), 10_000);
+    assert_eq!(suite.progress(id, BUYER), SaleProgressPersonal::default());
+}
+
+#[test]
+fn partial_fill_refunds_native_excess() {
+    let mut suite = Suite::new();
+    let id = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+    let buyer = Addr::unchecked(BUYER);
+
+    suite.participate_native(id, 1_200, &coins(1_200, DENOM), false).unwrap_err();
+    suite.participate_native(id, 1_200, &coins(1_200, DENOM), true).unwrap();
+
+    assert_eq!(suite.native_balance(&buyer), 9_000);
+    assert_eq!(suite.native_balance(&suite.launchpad), 1_000);
+    assert_eq!(suite.progress(id, BUYER).cur_spent, Uint128::new(1_000));
+}
+
+#[test]
+fn owner_allocation_is_carved_out_of_the_hard_cap() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        owner_allocation: Uint128::new(200),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    // buyers raise the 800 left of the hard cap for the whole sale amount
+    let res: CurrentPriceResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::CurrentPrice { id })
+        .unwrap();
+    assert_eq!(res.price, Decimal::percent(40));
+    suite.participate_native(id, 1_000, &coins(1_000, DENOM), true).unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(BUYER)), 10_000 - 800);
+    assert_eq!(suite.progress(id, BUYER).token_got, Uint128::new(2_000));
+
+    // the raise stops short of the hard cap, the sale is filled all the same
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Filled);
+    let err = suite.participate_from(OTHER, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::AlreadyFilled.to_string()
+    );
+}
+
+#[test]
+fn participate_with_cw20_currency() {
+    let mut suite = Suite::new();
+    let currency = suite.create_currency(BUYER, 5_000);
+    let id = suite.create_presale(AssetInfoUnchecked::cw20(currency.as_str()));
+    let buyer = Addr::unchecked(BUYER);
+
+    // cw20 currencies cannot be declared without being sent
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::cw20(currency.clone(), 100u128),
+                allow_partial: false,
+                referral_code: None,
+                merkle_proof: None,
+                voucher: None,
+                min_tokens_out: None,
+                max_price: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            currency.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: suite.launchpad.to_string(),
+                amount: Uint128::new(1_500),
+                msg: to_json_binary(&ReceiveMsg::ParticipateHook {
+                    id,
+                    allow_partial: true,
+                    referral_code: None,
+                    merkle_proof: None,
+                    voucher: None,
+                    min_tokens_out: None,
+                    max_price: None,
+                })
+                .unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(suite.cw20_balance(&currency, &buyer), 4_000);
+    assert_eq!(suite.cw20_balance(&currency, &suite.launchpad), 1_000);
+
+    let progress = suite.progress(id, BUYER);
+    assert_eq!(progress.cur_spent, Uint128::new(1_000));
+    assert_eq!(progress.token_got, Uint128::new(2_000));
+}
+
+#[test]
+fn participate_hook_rejects_other_cw20() {
+    let mut suite = Suite::new();
+    let currency = suite.create_currency(BUYER, 5_000);
+    let other = suite.create_currency(BUYER, 5_000);
+    let id = suite.create_presale(AssetInfoUnchecked::cw20(currency.as_str()));
+
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(BUYER),
+            other.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: suite.launchpad.to_string(),
+                amount: Uint128::new(100),
+                msg: to_json_binary(&ReceiveMsg::ParticipateHook {
+                    id,
+                    allow_partial: false,
+                    referral_code: None,
+                    merkle_proof: None,
+                    voucher: None,
+                    min_tokens_out: None,
+                    max_price: None,
+                })
+                .unwrap(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+    assert_eq!(suite.cw20_balance(&other, &Addr::unchecked(BUYER)), 5_000);
+}
+
+#[test]
+fn create_presale_for_existing_token() {
+    let mut suite = Suite::new();
+    let token = suite.create_currency(OWNER, 5_000);
+    let param = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let send = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: suite.launchpad.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&ReceiveMsg::CreatePresale {
+            param: param.clone(),
+        })
+        .unwrap(),
+    };
+    let (short, enough) = (send(1_999), send(2_000));
+
+    // inventory must cover the tokens for sale
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), token.clone(), &short, &[])
+        .unwrap_err();
+
+    let res = suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), token.clone(), &enough, &[])
+        .unwrap();
+    let id = sale_id(&res);
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.sale.token_addr, token);
+    assert_eq!(sale.sale.owner, Addr::unchecked(OWNER));
+
+    let escrow: EscrowResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Escrow { id })
+        .unwrap();
+    assert_eq!(escrow.escrowed, Uint128::new(2_000));
+    assert_eq!(escrow.balance, Uint128::new(2_000));
+    assert!(escrow.is_solvent);
+    assert_eq!(suite.cw20_balance(&token, &Addr::unchecked(OWNER)), 3_000);
+}
+
+#[test]
+fn sale_token_carries_decimals_and_marketing_info() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        token_decimals: 8,
+        token_marketing: Some(OWNER.to_string()),
+        token_logo: Some(Logo::Url("https://sale.io/logo.png".to_string())),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let create = |suite: &mut Suite, param: SellParam| {
+        suite.app.execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param,
+            },
+            &[],
+        )
+    };
+    let id = sale_id(&create(&mut suite, param.clone()).unwrap());
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&sale.sale.token_addr, &cw20::Cw20QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(token_info.decimals, 8);
+    assert_eq!(token_info.symbol, "SALE");
+    let marketing: cw20::MarketingInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&sale.sale.token_addr, &cw20::Cw20QueryMsg::MarketingInfo {})
+        .unwrap();
+    assert_eq!(
+        marketing,
+        cw20::MarketingInfoResponse {
+            project: Some("Sale".to_string()),
+            description: Some("Sale token".to_string()),
+            logo: Some(cw20::LogoInfo::Url("https://sale.io/logo.png".to_string())),
+            marketing: Some(Addr::unchecked(OWNER)),
+        }
+    );
+
+    // invalid metadata fails the creation instead of the token instantiation
+    let png_header = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    for (logo, error) in [
+        (Logo::Url(String::new()), "Logo url cannot be empty"),
+        (
+            Logo::Embedded(EmbeddedLogo::Svg(Binary::from(b"<svg></svg>".to_vec()))),
+            "Invalid xml preamble for SVG",
+        ),
+        (
+            Logo::Embedded(EmbeddedLogo::Png(Binary::from(b"GIF89a".to_vec()))),
+            "Invalid png header",
+        ),
+        (
+            Logo::Embedded(EmbeddedLogo::Png(Binary::from([png_header, vec![0; 5 * 1024]].concat()))),
+            "Logo binary data exceeds 5KB limit",
+        ),
+    ] {
+        let param = SellParam {
+            token_logo: Some(logo),
+            ..param.clone()
+        };
+        let err = create(&mut suite, param).unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap().to_string(),
+            ContractError::sell(error).to_string()
+        );
+    }
+    let err = create(
+        &mut suite,
+        SellParam {
+            token_decimals: 19,
+            ..param
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::sell("Generic error: Decimals must not exceed 18").to_string()
+    );
+}
+
+#[test]
+fn token_replies_map_to_their_sale() {
+    let mut suite = Suite::new();
+    let msgs = ["ONE", "TWO"]
+        .iter()
+        .map(|symbol| {
+            let param = SellParam {
+                token_symbol: symbol.to_string(),
+                ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+            };
+            WasmMsg::Execute {
+                contract_addr: suite.launchpad.to_string(),
+                msg: to_json_binary(&ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into()
+        })
+        .collect();
+
+    // both sales are created in a single transaction
+    suite.app.execute_multi(Addr::unchecked(OWNER), msgs).unwrap();
+
+    for (id, symbol) in [(1, "ONE"), (2, "TWO")] {
+        let sale: SaleResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+            .unwrap();
+        let token_info: cw20::TokenInfoResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&sale.sale.token_addr, &cw20::Cw20QueryMsg::TokenInfo {})
+            .unwrap();
+        assert_eq!(token_info.symbol, symbol);
+
+        let escrow: EscrowResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Escrow { id })
+            .unwrap();
+        assert_eq!(escrow.balance, Uint128::new(2_000));
+        assert!(escrow.is_solvent);
+    }
+}
+
+#[test]
+fn cancelled_sale_refunds_everyone() {
+    let mut suite = Suite::new();
+    let id = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+    let buyer = Addr::unchecked(BUYER);
+    let owner = Addr::unchecked(OWNER);
+    suite.participate_native(id, 300, &coins(300, DENOM), false).unwrap();
+
+    let cancel = ExecuteMsg::CancelSale {
+        id,
+        reason: "exploit".to_string(),
+    };
+    // the sale already started, only the admin can still cancel it
+    suite
+        .app
+        .execute_contract(owner.clone(), suite.launchpad.clone(), &cancel, &[])
+        .unwrap_err();
+    suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &cancel, &[])
+        .unwrap();
+
+    suite.participate_native(id, 100, &coins(100, DENOM), false).unwrap_err();
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap_err();
+
+    let refund = ExecuteMsg::RefundMsg { id };
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &refund, &[])
+        .unwrap();
+    suite
+        .app
+        .execute_contract(owner.clone(), suite.launchpad.clone(), &refund, &[])
+        .unwrap();
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Cancelled);
+    assert_eq!(suite.native_balance(&buyer), 10_000);
+    assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &owner), 2_000);
+    assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &suite.launchpad), 0);
+}
+
+#[test]
+fn purchased_tokens_follow_the_vesting_schedule() {
+    let mut suite = Suite::new();
+    let create = |suite: &mut Suite, vesting: VestingSchedule| {
+        let param = SellParam {
+            vesting: Some(vesting),
+            ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+        };
+        sale_id(
+            &suite
+                .app
+                .execute_contract(
+                    Addr::unchecked(OWNER),
+                    suite.launchpad.clone(),
+                    &ExecuteMsg::CreatePresaleMsg {
+                        amount: Uint128::zero(),
+                        param,
+                    },
+                    &[],
+                )
+                .unwrap(),
+        )
+    };
+    // 20% at the end, then linear over 400s after a 100s cliff
+    let linear = create(
+        &mut suite,
+        VestingSchedule {
+            tge_unlock: Decimal::percent(20),
+            cliff: 100,
+            duration: 400,
+            interval: None,
+        },
+    );
+    // a quarter every 100s from the end
+    let stepped = create(
+        &mut suite,
+        VestingSchedule {
+            tge_unlock: Decimal::zero(),
+            cliff: 0,
+            duration: 400,
+            interval: Some(100),
+        },
+    );
+    suite.advance(10);
+    suite.participate_from(BUYER, linear, 500).unwrap();
+    suite.participate_from(OTHER, stepped, 500).unwrap();
+    suite.advance(990);
+
+    let schedule = [
+        // seconds since the previous step, vested on each sale
+        (1, 200, 0),
+        (49, 200, 0),
+        (100, 300, 250),
+        (250, 800, 1_000),
+        (100, 1_000, 1_000),
+    ];
+    for (elapsed, linear_vested, stepped_vested) in schedule.iter() {
+        suite.advance(*elapsed);
+        for (id, address, vested) in [(linear, BUYER, *linear_vested), (stepped, OTHER, *stepped_vested)].iter() {
+            let before: VestingResponse = suite
+                .app
+                .wrap()
+                .query_wasm_smart(
+                    &suite.launchpad,
+                    &QueryMsg::Vesting {
+                        id: *id,
+                        address: Addr::unchecked(*address),
+                    },
+                )
+                .unwrap();
+            assert_eq!(before.total, Uint128::new(1_000));
+            assert_eq!(before.vested, Uint128::new(*vested));
+            assert_eq!(before.claimable, before.vested - before.claimed);
+
+            let res = suite.app.execute_contract(
+                Addr::unchecked(*address),
+                suite.launchpad.clone(),
+                &ExecuteMsg::ClaimMsg { id: *id },
+                &[],
+            );
+            match (before.claimable.is_zero(), before.claimed == before.total) {
+                (true, false) => assert_eq!(
+                    res.unwrap_err().downcast::<ContractError>().unwrap().to_string(),
+                    ContractError::claim("No vested token to claim yet").to_string()
+                ),
+                (true, true) => assert_eq!(
+                    res.unwrap_err().downcast::<ContractError>().unwrap().to_string(),
+                    ContractError::claim("Already claim token").to_string()
+                ),
+                (false, _) => {
+                    res.unwrap();
+                }
+            }
+
+            let sale: SaleResponse = suite
+                .app
+                .wrap()
+                .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id: *id })
+                .unwrap();
+            let address = Addr::unchecked(*address);
+            assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &address), *vested);
+            assert_eq!(suite.progress(*id, address.as_str()).token_claimed, Uint128::new(*vested));
+        }
+    }
+}
+
+#[test]
+fn owner_allocation_vests_while_the_raise_settles_once() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        owner_allocation: Uint128::new(500),
+        owner_vesting: Some(VestingSchedule {
+            tge_unlock: Decimal::zero(),
+            cliff: 200,
+            duration: 200,
+            interval: None,
+        }),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 400).unwrap();
+    suite.advance(991);
+
+    let owner = Addr::unchecked(OWNER);
+    let claim = |suite: &mut Suite| {
+        suite
+            .app
+            .execute_contract(owner.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+    };
+    let sale = |suite: &Suite| -> SaleResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+            .unwrap()
+    };
+    let token = sale(&suite).sale.token_addr;
+
+    // 400 of the 500 left to buyers sold 1_600 tokens, the raise and the unsold
+    // tokens are settled before the owner allocation vests
+    claim(&mut suite).unwrap();
+    assert_eq!(suite.native_balance(&owner), 400);
+    assert_eq!(suite.cw20_balance(&token, &owner), 400);
+
+    // elapsed since the previous step, owner allocation vested
+    for (elapsed, vested) in [(99, 0), (200, 250), (100, 500)].iter() {
+        suite.advance(*elapsed);
+        let res = sale(&suite);
+        assert_eq!(res.owner_vested, Uint128::new(*vested));
+        assert_eq!(res.owner_claimable, res.owner_vested - res.progress.owner_token_claimed);
+
+        match res.owner_claimable.is_zero() {
+            true => assert_eq!(
+                claim(&mut suite).unwrap_err().downcast::<ContractError>().unwrap().to_string(),
+                ContractError::claim("No vested owner allocation to claim yet").to_string()
+            ),
+            false => {
+                claim(&mut suite).unwrap();
+            }
+        }
+        assert_eq!(sale(&suite).owner_claimable, Uint128::zero());
+        assert_eq!(suite.native_balance(&owner), 400);
+        assert_eq!(suite.cw20_balance(&token, &owner), 400 + vested);
+    }
+
+    assert_eq!(
+        claim(&mut suite).unwrap_err().downcast::<ContractError>().unwrap().to_string(),
+        ContractError::claim("Already claim currency and token").to_string()
+    );
+}
+
+#[test]
+fn ended_sale_cannot_be_cancelled_into_a_second_refund() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        sale_type: SaleType::Overflow,
+        vesting: Some(VestingSchedule {
+            tge_unlock: Decimal::zero(),
+            cliff: 0,
+            duration: 1_000,
+            interval: None,
+        }),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 1_500).unwrap();
+    suite.participate_from(OTHER, id, 500).unwrap();
+    suite.advance(1_000);
+
+    // the first claim settles the allocation and returns the oversubscribed currency
+    let buyer = Addr::unchecked(BUYER);
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&buyer), 10_000 - 750);
+
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CancelSale {
+                id,
+                reason: "late".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::cancel("Sale already ended").to_string()
+    );
+
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &ExecuteMsg::RefundMsg { id }, &[])
+        .unwrap_err();
+    assert_eq!(suite.native_balance(&buyer), 10_000 - 750);
+
+    // the raise still covers the owner and the remaining refund
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OTHER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 1_000);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
+
+#[test]
+fn fees_are_charged_on_successful_sales_only() {
+    let mut suite = Suite::with_config(
+        500,
+        250,
+        ReferralConfig {
+            sale_percentage: Uint128::zero(),
+            participant_percentage: Uint128::zero(),
+            base: ReferralBase::PlatformFee,
+        },
+    );
+    let collector = Addr::unchecked(COLLECTOR);
+    let owner = Addr::unchecked(OWNER);
+    let succeeded = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+    let failed = suite.create_presale(AssetInfoUnchecked::native(DENOM));
+    let sale = |suite: &Suite, id: u64| -> SaleResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+            .unwrap()
+    };
+    // the largest token fee is escrowed along with the sale amount
+    assert_eq!(sale(&suite, succeeded).progress.token_escrowed, Uint128::new(2_050));
+
+    suite.participate_from(BUYER, succeeded, 400).unwrap();
+    suite.participate_from(OTHER, failed, 50).unwrap();
+    suite.advance(1_000);
+
+    // 5% of the 400 raised and 2.5% of the 800 tokens sold go to the collector
+    let token = sale(&suite, succeeded).sale.token_addr;
+    for sender in [OWNER, BUYER].iter() {
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(*sender),
+                suite.launchpad.clone(),
+                &ExecuteMsg::ClaimMsg { id: succeeded },
+                &[],
+            )
+            .unwrap();
+    }
+    let progress = sale(&suite, succeeded).progress;
+    assert_eq!(progress.cur_fee, Uint128::new(20));
+    assert_eq!(progress.token_fee, Uint128::new(20));
+    assert_eq!(suite.native_balance(&collector), 20);
+    assert_eq!(suite.cw20_balance(&token, &collector), 20);
+    assert_eq!(suite.native_balance(&owner), 380);
+    assert_eq!(suite.cw20_balance(&token, &owner), 1_230);
+    assert_eq!(suite.cw20_balance(&token, &Addr::unchecked(BUYER)), 800);
+    assert_eq!(suite.cw20_balance(&token, &suite.launchpad), 0);
+
+    // the sale below its soft cap returns everything, the reserved token fee included
+    assert_eq!(sale(&suite, failed).status, crate::state::SaleStatus::Failed);
+    let token = sale(&suite, failed).sale.token_addr;
+    for sender in [OWNER, OTHER].iter() {
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(*sender),
+                suite.launchpad.clone(),
+                &ExecuteMsg::RefundMsg { id: failed },
+                &[],
+            )
+            .unwrap();
+    }
+    let progress = sale(&suite, failed).progress;
+    assert_eq!(progress.cur_fee, Uint128::zero());
+    assert_eq!(progress.token_fee, Uint128::zero());
+    assert_eq!(suite.native_balance(&collector), 20);
+    assert_eq!(suite.cw20_balance(&token, &collector), 0);
+    assert_eq!(suite.cw20_balance(&token, &owner), 2_050);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OTHER)), 10_000);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
+
+#[test]
+fn merkle_allowlist_caps_allocation() {
+    use sha2::{Digest, Sha256};
+
+    let leaf = |address: &str, amount: u128| -> [u8; 32] {
+        Sha256::digest(format!("{}{}", address, amount).as_bytes()).into()
+    };
+    let (buyer_leaf, other_leaf) = (leaf(BUYER, 300), leaf("other", 500));
+    let mut pair = [buyer_leaf, other_leaf];
+    pair.sort_unstable();
+    let root: [u8; 32] = Sha256::digest(pair.concat()).into();
+
+    let mut suite = Suite::new();
+    let param = SellParam {
+        rounds: vec![
+            round(suite.now() + 10, suite.now() + 510, true),
+            round(suite.now() + 510, suite.now() + 1_000, false),
+        ],
+        merkle_root: Some(hex::encode(root)),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    let proof = crate::state::MerkleProof {
+        max_allocation: Uint128::new(300),
+        proof: vec![hex::encode(other_leaf)],
+    };
+    let participate = |amount: u128, merkle_proof: Option<crate::state::MerkleProof>| {
+        ExecuteMsg::ParticipateMsg {
+            id,
+            cur: Asset::native(DENOM, amount),
+            allow_partial: false,
+            referral_code: None,
+            merkle_proof,
+            voucher: None,
+            min_tokens_out: None,
+            max_price: None,
+        }
+    };
+    let buyer = Addr::unchecked(BUYER);
+
+    let res: crate::msg::MerkleProofResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::VerifyMerkleProof {
+                id,
+                address: buyer.clone(),
+                proof: proof.clone(),
+            },
+        )
+        .unwrap();
+    assert!(res.is_valid);
+    assert_eq!(res.remaining, Uint128::new(300));
+
+    // no proof during the whitelist window
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &participate(100, None), &coins(100, DENOM))
+        .unwrap_err();
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.launchpad.clone(),
+            &participate(200, Some(proof.clone())),
+            &coins(200, DENOM),
+        )
+        .unwrap();
+    // above the leaf allocation
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.launchpad.clone(),
+            &participate(200, Some(proof.clone())),
+            &coins(200, DENOM),
+        )
+        .unwrap_err();
+
+    // public phase
+    suite.advance(500);
+    suite
+        .app
+        .execute_contract(buyer, suite.launchpad.clone(), &participate(200, None), &coins(200, DENOM))
+        .unwrap();
+    assert_eq!(suite.progress(id, BUYER).cur_spent, Uint128::new(400));
+}
+
+#[test]
+fn merkle_root_is_updated_by_the_owner_before_start() {
+    use sha2::{Digest, Sha256};
+
+    // a single leaf tree, the root is the leaf and the proof is empty
+    let root: [u8; 32] = Sha256::digest(format!("{}{}", BUYER, 300).as_bytes()).into();
+    let proof = crate::state::MerkleProof {
+        max_allocation: Uint128::new(300),
+        proof: vec![],
+    };
+
+    let mut suite = Suite::new();
+    let param = SellParam {
+        rounds: vec![round(suite.now() + 10, suite.now() + 1_000, true)],
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let update = |suite: &mut Suite, sender: &str, merkle_root: Option<String>| {
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(sender),
+                suite.launchpad.clone(),
+                &ExecuteMsg::UpdateMerkleRoot { id, merkle_root },
+                &[],
+            )
+            .map_err(|err| err.downcast::<ContractError>().unwrap().to_string())
+    };
+    let is_valid = |suite: &Suite| -> bool {
+        let res: crate::msg::MerkleProofResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::VerifyMerkleProof {
+                    id,
+                    address: Addr::unchecked(BUYER),
+                    proof: proof.clone(),
+                },
+            )
+            .unwrap();
+        res.is_valid
+    };
+
+    assert_eq!(
+        update(&mut suite, BUYER, Some(hex::encode(root))).unwrap_err(),
+        ContractError::OnlySaleOwner.to_string()
+    );
+    assert_eq!(
+        update(&mut suite, OWNER, Some("root".to_string())).unwrap_err(),
+        ContractError::sell("Merkle root must be a hex encoded sha256 hash").to_string()
+    );
+    assert!(!is_valid(&suite));
+
+    update(&mut suite, OWNER, Some(hex::encode(root))).unwrap();
+    assert!(is_valid(&suite));
+    update(&mut suite, OWNER, None).unwrap();
+    assert!(!is_valid(&suite));
+    update(&mut suite, OWNER, Some(hex::encode(root))).unwrap();
+
+    // the allowlist is frozen once the sale starts
+    suite.advance(10);
+    assert_eq!(
+        update(&mut suite, OWNER, None).unwrap_err(),
+        ContractError::whitelist("Merkle root can only be updated before start").to_string()
+    );
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(BUYER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::ParticipateMsg {
+                id,
+                cur: Asset::native(DENOM, 300u128),
+                allow_partial: false,
+                referral_code: None,
+                merkle_proof: Some(proof.clone()),
+                voucher: None,
+                min_tokens_out: None,
+                max_price: None,
+            },
+            &coins(300, DENOM),
+        )
+        .unwrap();
+    assert_eq!(suite.progress(id, BUYER).cur_spent, Uint128::new(300));
+}
+
+#[test]
+fn signed_voucher_grants_allocation_once() {
+    use crate::state::{SignedVoucher, Voucher};
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    let signing_key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+    let public_key = signing_key.verifying_key().to_encoded_point(true);
+
+    let mut suite = Suite::new();
+    let param = SellParam {
+        rounds: vec![
+            round(suite.now() + 10, suite.now() + 510, true),
+            round(suite.now() + 510, suite.now() + 1_000, false),
+        ],
+        voucher_signer: Some(public_key.as_bytes().into()),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    let sign = |voucher: Voucher| {
+        let signature: Signature = signing_key.sign(&cosmwasm_std::to_json_vec(&voucher).unwrap());
+        SignedVoucher {
+            voucher,
+            signature: signature.to_bytes().as_slice().into(),
+        }
+    };
+    let voucher = Voucher {
+        sale_id: id,
+        buyer: BUYER.to_string(),
+        max_amount: Uint128::new(300),
+        expiry: suite.now() + 100,
+        nonce: 1,
+    };
+    let participate = |amount: u128, voucher: SignedVoucher| ExecuteMsg::ParticipateMsg {
+        id,
+        cur: Asset::native(DENOM, amount),
+        allow_partial: false,
+        referral_code: None,
+        merkle_proof: None,
+        voucher: Some(voucher),
+        min_tokens_out: None,
+        max_price: None,
+    };
+    let buyer = Addr::unchecked(BUYER);
+
+    // above the voucher allocation
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.launchpad.clone(),
+            &participate(400, sign(voucher.clone())),
+            &coins(400, DENOM),
+        )
+        .unwrap_err();
+    // tampered voucher
+    let mut tampered = sign(voucher.clone());
+    tampered.voucher.max_amount = Uint128::new(1_000);
+    suite
+        .app
+        .execute_contract(buyer.clone(), suite.launchpad.clone(), &participate(400, tampered), &coins(400, DENOM))
+        .unwrap_err();
+
+    suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.launchpad.clone(),
+            &participate(300, sign(voucher.clone())),
+            &coins(300, DENOM),
+        )
+        .unwrap();
+    // nonce cannot be replayed
+    suite
+        .app
+        .execute_contract(buyer, suite.launchpad.clone(), &participate(1, sign(voucher)), &coins(1, DENOM))
+        .unwrap_err();
+
+    assert_eq!(suite.progress(id, BUYER).cur_spent, Uint128::new(300));
+}
+
+#[test]
+fn rounds_apply_their_own_allowlist_cap_and_price() {
+    let mut suite = Suite::new();
+    let now = suite.now();
+    let param = SellParam {
+        rounds: vec![
+            Round {
+                max_cur_alloc_per: Some(Uint128::new(200)),
+                price: Some(cosmwasm_std::Decimal::percent(400)),
+                ..round(now + 10, now + 310, true)
+            },
+            round(now + 400, now + 1_000, false),
+        ],
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::AddToWhitelist {
+                id,
+                round: 0,
+                addresses: vec![BUYER.to_string()],
+            },
+            &[],
+        )
+        .unwrap();
+    // the public round has no allowlist
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::AddToWhitelist {
+                id,
+                round: 1,
+                addresses: vec![BUYER.to_string()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    suite.advance(10);
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Ongoing { round: Some(0) });
+
+    // above the round cap
+    suite.participate_native(id, 300, &coins(300, DENOM), false).unwrap_err();
+    suite.participate_native(id, 200, &coins(200, DENOM), false).unwrap();
+    assert_eq!(suite.progress(id, BUYER).token_got, Uint128::new(800));
+
+    // gap between rounds
+    suite.advance(350);
+    suite.participate_native(id, 100, &coins(100, DENOM), false).unwrap_err();
+
+    // public round at the sale price, the remaining 1_200 tokens sell for 600
+    suite.advance(100);
+    suite.participate_native(id, 1_000, &coins(1_000, DENOM), true).unwrap();
+    assert_eq!(suite.progress(id, BUYER).token_got, Uint128::new(2_000));
+    assert_eq!(suite.native_balance(&Addr::unchecked(BUYER)), 10_000 - 800);
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Filled);
+    assert_eq!(sale.progress.round_raised, vec![Uint128::new(200), Uint128::new(600)]);
+}
+
+#[test]
+fn sale_owner_manages_a_paginated_whitelist_for_its_window() {
+    let mut suite = Suite::new();
+    let now = suite.now();
+    let param = SellParam {
+        rounds: vec![round(now + 10, now + 110, true), round(now + 110, now + 1_000, false)],
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let update = |suite: &mut Suite, sender: &str, is_add: bool, addresses: &[&str]| {
+        let addresses = addresses.iter().map(|address| address.to_string()).collect();
+        let msg = match is_add {
+            true => ExecuteMsg::AddToWhitelist { id, round: 0, addresses },
+            false => ExecuteMsg::RemoveFromWhitelist { id, round: 0, addresses },
+        };
+        suite
+            .app
+            .execute_contract(Addr::unchecked(sender), suite.launchpad.clone(), &msg, &[])
+    };
+    let whitelist = |suite: &Suite, start_after: Option<&str>, limit: Option<u64>| -> Vec<Addr> {
+        let res: WhitelistResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::Whitelist {
+                    id,
+                    round: 0,
+                    start_after: start_after.map(String::from),
+                    limit,
+                },
+            )
+            .unwrap();
+        res.addresses
+    };
+
+    for is_add in [true, false].iter() {
+        let err = update(&mut suite, BUYER, *is_add, &[BUYER]).unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap().to_string(),
+            ContractError::OnlySaleOwner.to_string()
+        );
+    }
+    update(&mut suite, OWNER, true, &[OTHER, BUYER, "third"]).unwrap();
+    assert_eq!(
+        whitelist(&suite, None, Some(2)),
+        vec![Addr::unchecked(BUYER), Addr::unchecked(OTHER)]
+    );
+    assert_eq!(whitelist(&suite, Some(OTHER), Some(2)), vec![Addr::unchecked("third")]);
+
+    update(&mut suite, OWNER, false, &[OTHER]).unwrap();
+    assert_eq!(
+        whitelist(&suite, None, None),
+        vec![Addr::unchecked(BUYER), Addr::unchecked("third")]
+    );
+
+    // the whitelisted round runs from the start, the public round follows
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 100).unwrap();
+    let err = suite.participate_from(OTHER, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::whitelist("Buyer address is not whitelisted").to_string()
+    );
+    suite.advance(99);
+    suite.participate_from(OTHER, id, 100).unwrap_err();
+    suite.advance(1);
+    suite.participate_from(OTHER, id, 100).unwrap();
+}
+
+#[test]
+fn per_wallet_caps_apply_to_each_buyer() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        max_cur_alloc_per: Some(Uint128::new(300)),
+        min_cur_alloc_per: Some(Uint128::new(50)),
+        max_participants: Some(2),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    let err = suite.participate_from(BUYER, id, 20).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::BelowMinContribution(Uint128::new(50)).to_string()
+    );
+    suite.participate_from(BUYER, id, 200).unwrap();
+    // the cap is per wallet, not on the sale total
+    suite.participate_from(OTHER, id, 300).unwrap();
+    let err = suite.participate_from(BUYER, id, 150).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::AboveMaxContribution(Uint128::new(300)).to_string()
+    );
+
+    let limit: ContributionLimitResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::ContributionLimit {
+                id,
+                address: Addr::unchecked(BUYER),
+            },
+        )
+        .unwrap();
+    assert_eq!(limit.remaining, Uint128::new(100));
+    let limit: ContributionLimitResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::ContributionLimit {
+                id,
+                address: Addr::unchecked(ADMIN),
+            },
+        )
+        .unwrap();
+    assert_eq!(limit.remaining, Uint128::zero());
+
+    // third participant
+    suite
+        .app
+        .send_tokens(Addr::unchecked(OTHER), Addr::unchecked(ADMIN), &coins(100, DENOM))
+        .unwrap();
+    let err = suite.participate_from(ADMIN, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::ParticipantLimit(2).to_string()
+    );
+    suite.participate_from(BUYER, id, 100).unwrap();
+}
+
+#[test]
+fn sale_fails_below_min_participants() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        min_participants: Some(2),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    // above the soft cap, but a single participant
+    suite.participate_from(BUYER, id, 500).unwrap();
+    suite.advance(1_000);
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Failed);
+    suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &ExecuteMsg::RefundMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(BUYER)), 10_000);
+}
+
+#[test]
+fn sell_params_are_checked_against_config() {
+    let mut suite = Suite::new();
+    suite.add_currency(AssetInfoUnchecked::native(DENOM), 200);
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            suite.launchpad.clone(),
+            &ExecuteMsg::UpdateConfigMsg {
+                min_token_sale_amt: Uint128::new(1_000),
+                token_code_id: suite.cw20_code_id,
+                fee_percentage: Uint128::zero(),
+                token_fee_percentage: Uint128::zero(),
+                fee_collector: ADMIN.to_string(),
+                referral: ReferralConfig {
+                    sale_percentage: Uint128::zero(),
+                    participant_percentage: Uint128::zero(),
+                    base: ReferralBase::PlatformFee,
+                },
+            },
+            &[],
+        )
+        .unwrap();
+
+    let base = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let validate = |param: SellParam| -> ValidateSellParamResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::ValidateSellParam {
+                    owner: Addr::unchecked(OWNER),
+                    token: None,
+                    param,
+                },
+            )
+            .unwrap()
+    };
+
+    let res = validate(base.clone());
+    assert_eq!(res.error.as_deref(), Some("Sell: Soft cap must be gteq than 200"));
+    let valid = SellParam {
+        soft_cap: Uint128::new(200),
+        ..base.clone()
+    };
+    assert!(validate(valid.clone()).is_valid);
+
+    for (param, error) in [
+        (
+            SellParam {
+                token_sale_amt: Uint128::new(500),
+                ..valid.clone()
+            },
+            "Sell: Token sale amount must be gteq than 1000",
+        ),
+        (
+            SellParam {
+                soft_cap: Uint128::new(2_000),
+                ..valid.clone()
+            },
+            "Sell: Soft cap must be lteq than hard cap",
+        ),
+        (
+            SellParam {
+                owner_allocation: Uint128::new(1_000),
+                ..valid.clone()
+            },
+            "Sell: Owner allocation must be less than hard cap",
+        ),
+        (
+            SellParam {
+                end: valid.start + crate::state::MAX_SALE_DURATION + 1,
+                ..valid.clone()
+            },
+            "Sell: Sale duration must be lteq than 7776000 seconds",
+        ),
+        (
+            SellParam {
+                hard_cap: Uint128::new(u128::MAX),
+                ..valid.clone()
+            },
+            "Sell: Token price rounds to zero, raise token sale amount or lower hard cap",
+        ),
+        (
+            SellParam {
+                referrer: Some(OWNER.to_string()),
+                ..valid.clone()
+            },
+            "Sell: Sale referrer cannot be the sale owner",
+        ),
+        (
+            SellParam {
+                token_symbol: "X".to_string(),
+                ..valid.clone()
+            },
+            "Sell: Generic error: Ticker symbol is not in expected format [a-zA-Z\\-]{3,12}",
+        ),
+        (
+            SellParam {
+                token_logo: Some(Logo::Url(String::new())),
+                ..valid.clone()
+            },
+            "Sell: Logo url cannot be empty",
+        ),
+    ] {
+        let res = validate(param);
+        assert!(!res.is_valid);
+        assert_eq!(res.error.as_deref(), Some(error));
+    }
+
+    // an existing token brings its own metadata, only minted tokens are checked
+    let token = suite.create_currency(OWNER, 1_000);
+    let res: ValidateSellParamResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::ValidateSellParam {
+                owner: Addr::unchecked(OWNER),
+                token: Some(token),
+                param: SellParam {
+                    token_symbol: "X".to_string(),
+                    ..valid.clone()
+                },
+            },
+        )
+        .unwrap();
+    assert!(res.is_valid);
+
+    // the same checks guard sale creation
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param: base,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        "Sell: Soft cap must be gteq than 200"
+    );
+
+    // an owner naming themselves the sale referrer would earn a cut of the fee
+    let self_referred = SellParam {
+        referrer: Some(OWNER.to_string()),
+        ..valid
+    };
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param: self_referred.clone(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        "Sell: Sale referrer cannot be the sale owner"
+    );
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OTHER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param: self_referred,
+            },
+            &[],
+        )
+        .unwrap();
+}
+
+#[test]
+fn sales_must_quote_a_whitelisted_currency() {
+    let mut suite = Suite::new();
+    let create = |suite: &mut Suite, cur_info: AssetInfoUnchecked| {
+        let param = suite.sell_param(cur_info);
+        suite.app.execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param,
+            },
+            &[],
+        )
+    };
+
+    let err = create(&mut suite, AssetInfoUnchecked::native("uluna")).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        "Sell: Currency native:uluna is not whitelisted"
+    );
+
+    // only the admin manages currencies
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::AddCurrency {
+                info: AssetInfoUnchecked::native("uluna"),
+                min_soft_cap: Uint128::zero(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    suite.add_currency(AssetInfoUnchecked::native("uluna"), 0);
+    create(&mut suite, AssetInfoUnchecked::native("uluna")).unwrap();
+
+    let currency = suite.create_currency(BUYER, 1_000);
+    let res: CurrencyWhitelistResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::CurrencyWhitelist {})
+        .unwrap();
+    assert_eq!(res.native, vec!["uluna".to_string(), DENOM.to_string()]);
+    assert_eq!(res.token, vec![currency.clone()]);
+
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            suite.launchpad.clone(),
+            &ExecuteMsg::RemoveCurrency {
+                info: AssetInfoUnchecked::cw20(currency.as_str()),
+            },
+            &[],
+        )
+        .unwrap();
+    create(&mut suite, AssetInfoUnchecked::cw20(currency.as_str())).unwrap_err();
+}
+
+#[test]
+fn overflow_sale_allocates_pro_rata_and_refunds_on_claim() {
+    let mut suite = Suite::new();
+    let param = SellParam {
+        sale_type: SaleType::Overflow,
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    // commitments beyond the hard cap are accepted
+    suite.participate_from(BUYER, id, 1_500).unwrap();
+    suite.participate_from(OTHER, id, 500).unwrap();
+
+    let allocation: AllocationResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::Allocation {
+                id,
+                address: Addr::unchecked(BUYER),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        allocation,
+        AllocationResponse {
+            committed: Uint128::new(1_500),
+            token_amount: Uint128::new(1_500),
+            cur_refund: Uint128::new(750),
+            oversubscription: cosmwasm_std::Decimal::percent(200),
+        }
+    );
+
+    // no claim before the end
+    suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap_err();
+    suite.advance(1_000);
+
+    for (address, token, refund) in [(BUYER, 1_500, 750), (OTHER, 500, 250)] {
+        let address = Addr::unchecked(address);
+        let balance = suite.native_balance(&address);
+        suite
+            .app
+            .execute_contract(address.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+            .unwrap();
+        assert_eq!(suite.native_balance(&address), balance + refund);
+
+        let sale: SaleResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+            .unwrap();
+        assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &address), token);
+    }
+
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 1_000);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
+
+#[test]
+fn dutch_auction_settles_at_the_clearing_price() {
+
+    let mut suite = Suite::new();
+    let base = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let param = SellParam {
+        end: base.start + 1_000,
+        hard_cap: Uint128::new(2_000),
+        sale_type: SaleType::DutchAuction {
+            start_price: Decimal::one(),
+            floor_price: Decimal::percent(20),
+            decay: PriceDecay::Linear,
+        },
+        ..base
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+    let current_price = |suite: &Suite| -> Decimal {
+        let res: CurrentPriceResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::CurrentPrice { id })
+            .unwrap();
+        res.price
+    };
+
+    assert_eq!(current_price(&suite), Decimal::one());
+    suite.participate_from(BUYER, id, 500).unwrap();
+    assert_eq!(suite.progress(id, BUYER).token_got, Uint128::new(500));
+
+    suite.advance(500);
+    assert_eq!(current_price(&suite), Decimal::percent(60));
+    suite.participate_from(OTHER, id, 600).unwrap();
+    assert_eq!(suite.progress(id, OTHER).token_got, Uint128::new(1_000));
+
+    // not sold out, everybody pays the floor reached at the end
+    suite.advance(600);
+    assert_eq!(current_price(&suite), Decimal::percent(20));
+
+    for (address, token, refund) in [(BUYER, 500, 400), (OTHER, 1_000, 400)] {
+        let address = Addr::unchecked(address);
+        let balance = suite.native_balance(&address);
+        suite
+            .app
+            .execute_contract(address.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+            .unwrap();
+        assert_eq!(suite.native_balance(&address), balance + refund);
+
+        let sale: SaleResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+            .unwrap();
+        assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &address), token);
+    }
+
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 300);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Ended);
+    // unsold tokens go back to the owner
+    assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &Addr::unchecked(OWNER)), 500);
+}
+
+#[test]
+fn bonding_curve_charges_the_integral() {
+
+    let mut suite = Suite::new();
+    let create = |suite: &mut Suite, curve: BondingCurve, hard_cap: u128| -> u64 {
+        // base units are whole tokens, the curve is priced per base unit
+        let param = SellParam {
+            hard_cap: Uint128::new(hard_cap),
+            sale_type: SaleType::BondingCurve { curve },
+            token_decimals: 0,
+            ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+        };
+        sale_id(
+            &suite
+                .app
+                .execute_contract(
+                    Addr::unchecked(OWNER),
+                    suite.launchpad.clone(),
+                    &ExecuteMsg::CreatePresaleMsg {
+                        amount: Uint128::zero(),
+                        param,
+                    },
+                    &[],
+                )
+                .unwrap(),
+        )
+    };
+    let simulate = |suite: &Suite, id: u64, cur_amount: u128| -> SimulateBuyResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::SimulateBuy {
+                    id,
+                    cur_amount: Uint128::new(cur_amount),
+                },
+            )
+            .unwrap()
+    };
+
+    // 0.5 + 0.0005 * sold, the whole supply costs 2_000
+    let linear = BondingCurve::Linear {
+        base_price: Decimal::percent(50),
+        slope: Decimal::from_ratio(5u128, 10_000u128),
+    };
+    let id = create(&mut suite, linear, 2_000);
+    // e^ln2 doubles the price: 1_000 buys ln2 / 0.0005 tokens
+    let exponential = BondingCurve::Exponential {
+        base_price: Decimal::percent(50),
+        growth: Decimal::from_ratio(5u128, 10_000u128),
+    };
+    let exp_id = create(&mut suite, exponential, 1_719);
+    suite.advance(10);
+
+    assert_eq!(simulate(&suite, exp_id, 1_000).token_amount, Uint128::new(1_386));
+
+    let quote = simulate(&suite, id, 600);
+    assert_eq!(quote.token_amount, Uint128::new(843));
+    assert_eq!(quote.cur_spent, Uint128::new(600));
+    assert_eq!(quote.average_price, Decimal::from_ratio(600u128, 843u128));
+
+    let participate = |min_tokens_out: u128| ExecuteMsg::ParticipateMsg {
+        id,
+        cur: Asset::native(DENOM, 600u128),
+        allow_partial: false,
+        referral_code: None,
+        merkle_proof: None,
+        voucher: None,
+        min_tokens_out: Some(Uint128::new(min_tokens_out)),
+        max_price: None,
+    };
+    let err = suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &participate(900), &coins(600, DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::Slippage(Uint128::new(900), Uint128::new(843)).to_string()
+    );
+    suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &participate(843), &coins(600, DENOM))
+        .unwrap();
+    assert_eq!(suite.progress(id, BUYER).token_got, Uint128::new(843));
+
+    // the price moved up the curve
+    let price: CurrentPriceResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::CurrentPrice { id })
+        .unwrap();
+    assert_eq!(price.price, Decimal::from_ratio(9_215u128, 10_000u128));
+    assert!(simulate(&suite, id, 600).token_amount < Uint128::new(843));
+
+    // the rest of the curve costs 2_000 - 599.1625, rounded up
+    suite.participate_from(OTHER, id, 1_401).unwrap();
+    assert_eq!(suite.progress(id, OTHER).token_got, Uint128::new(1_157));
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Filled);
+}
+
+#[test]
+fn bonding_curve_is_priced_per_whole_token() {
+    const WHOLE: u128 = 1_000_000_000_000_000_000;
+
+    let mut suite = Suite::new();
+    let create = |suite: &mut Suite, token_sale_amt: u128, curve: BondingCurve, hard_cap: u128| {
+        let param = SellParam {
+            token_sale_amt: Uint128::new(token_sale_amt),
+            hard_cap: Uint128::new(hard_cap),
+            sale_type: SaleType::BondingCurve { curve },
+            token_decimals: 18,
+            ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+        };
+        suite.app.execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param,
+            },
+            &[],
+        )
+    };
+
+    // the smallest slope no longer prices a million 18 decimals tokens out of the hard cap
+    create(
+        &mut suite,
+        1_000_000 * WHOLE,
+        BondingCurve::Linear {
+            base_price: Decimal::permille(1),
+            slope: Decimal::raw(1),
+        },
+        1_001,
+    )
+    .unwrap();
+    // nor is an exponential curve capped to a few base units of supply
+    create(
+        &mut suite,
+        1_000 * WHOLE,
+        BondingCurve::Exponential {
+            base_price: Decimal::one(),
+            growth: Decimal::permille(1),
+        },
+        2_000,
+    )
+    .unwrap();
+
+    // 1 + 0.002 * sold 6 decimals currency units per token, the 1_000 tokens cost 2_000
+    let id = sale_id(
+        &create(
+            &mut suite,
+            1_000 * WHOLE,
+            BondingCurve::Linear {
+                base_price: Decimal::one(),
+                slope: Decimal::permille(2),
+            },
+            2_000,
+        )
+        .unwrap(),
+    );
+    suite.advance(10);
+
+    // 600 buys (sqrt(3.4) - 1) / 0.002 tokens
+    let quote: SimulateBuyResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::SimulateBuy {
+                id,
+                cur_amount: Uint128::new(600),
+            },
+        )
+        .unwrap();
+    assert_eq!(quote.token_amount.u128() / WHOLE, 421);
+    assert_eq!(quote.cur_spent, Uint128::new(600));
+    suite.participate_from(BUYER, id, 600).unwrap();
+    assert_eq!(suite.progress(id, BUYER).token_got, quote.token_amount);
+
+    // the rest of the curve costs the other 1_400
+    suite.participate_from(OTHER, id, 1_400).unwrap();
+    assert_eq!(
+        suite.progress(id, OTHER).token_got,
+        Uint128::new(1_000 * WHOLE) - quote.token_amount
+    );
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Filled);
+}
+
+#[test]
+fn batch_auction_fills_the_highest_bids_at_a_uniform_price() {
+    let mut suite = Suite::new();
+    suite
+        .app
+        .send_tokens(Addr::unchecked(BUYER), Addr::unchecked(ADMIN), &coins(1_000, DENOM))
+        .unwrap();
+    let base = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let param = SellParam {
+        token_sale_amt: Uint128::new(1_000),
+        sale_type: SaleType::BatchAuction {
+            reserve_price: Decimal::percent(10),
+        },
+        ..base
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    let err = suite.bid_from(BUYER, id, 100, Decimal::percent(5)).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::buy("Bid max price is below the reserve price").to_string()
+    );
+    suite.bid_from(BUYER, id, 400, Decimal::percent(80)).unwrap();
+    suite.bid_from(OTHER, id, 300, Decimal::percent(50)).unwrap();
+    suite.bid_from(ADMIN, id, 100, Decimal::percent(20)).unwrap();
+    let err = suite.bid_from(BUYER, id, 100, Decimal::percent(90)).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::buy("Bid max price cannot be changed").to_string()
+    );
+
+    suite.advance(1_000);
+    let err = suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::claim("Auction is not settled yet").to_string()
+    );
+
+    // 400 at 0.8 does not cover 1_000 tokens, 300 more at 0.5 does, OTHER is filled for 100
+    suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &ExecuteMsg::SettleAuction { id }, &[])
+        .unwrap();
+    let res: CurrentPriceResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::CurrentPrice { id })
+        .unwrap();
+    assert_eq!(res.price, Decimal::percent(50));
+
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    for (address, token, refund) in [(BUYER, 800, 0), (OTHER, 200, 200)] {
+        let address = Addr::unchecked(address);
+        let balance = suite.native_balance(&address);
+        suite
+            .app
+            .execute_contract(address.clone(), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+            .unwrap();
+        assert_eq!(suite.native_balance(&address), balance + refund);
+        assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &address), token);
+    }
+
+    // the lowest bid is left out and takes its currency back
+    let err = suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::claim("Bid was not filled, please `refund` instead").to_string()
+    );
+    suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &ExecuteMsg::RefundMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(ADMIN)), 1_000);
+
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 500);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
+
+#[test]
+fn referral_rewards_come_out_of_the_fee_or_the_raise() {
+    // both configurations reward 2% of each referred contribution and 1% of the raise
+    let configs = [
+        (ReferralBase::PlatformFee, 2_000, 1_000, 720, 62),
+        (ReferralBase::Raised, 200, 100, 702, 80),
+    ];
+    for (base, participant_percentage, sale_percentage, owner_payout, fee_payout) in configs.iter().cloned() {
+        let mut suite = Suite::with_config(
+            1_000,
+            0,
+            ReferralConfig {
+                sale_percentage: Uint128::new(sale_percentage),
+                participant_percentage: Uint128::new(participant_percentage),
+                base,
+            },
+        );
+        let register = |suite: &mut Suite, sender: &str, code: &str| {
+            suite.app.execute_contract(
+                Addr::unchecked(sender),
+                suite.launchpad.clone(),
+                &ExecuteMsg::RegisterReferralCode { code: code.to_string() },
+                &[],
+            )
+        };
+        register(&mut suite, REFERRER, "ref").unwrap();
+        register(&mut suite, OWNER, "owner").unwrap();
+        let err = register(&mut suite, OTHER, "ref").unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap().to_string(),
+            ContractError::referral("Referral code already taken").to_string()
+        );
+        let err = register(&mut suite, OTHER, "").unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap().to_string(),
+            ContractError::referral("Invalid referral code length").to_string()
+        );
+        let res: ReferralCodeResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.launchpad, &QueryMsg::ReferralCode { code: "ref".to_string() })
+            .unwrap();
+        assert_eq!(res.referrer, Some(Addr::unchecked(REFERRER)));
+
+        let param = SellParam {
+            referrer: Some("promoter".to_string()),
+            ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+        };
+        let id = sale_id(
+            &suite
+                .app
+                .execute_contract(
+                    Addr::unchecked(OWNER),
+                    suite.launchpad.clone(),
+                    &ExecuteMsg::CreatePresaleMsg {
+                        amount: Uint128::zero(),
+                        param,
+                    },
+                    &[],
+                )
+                .unwrap(),
+        );
+        suite.advance(10);
+
+        for (code, msg) in [
+            ("unknown", "Referral code not found"),
+            ("owner", "Cannot use the sale owner's referral code"),
+        ]
+        .iter()
+        {
+            let err = suite.participate_referred(BUYER, id, 500, None, code).unwrap_err();
+            assert_eq!(
+                err.downcast::<ContractError>().unwrap().to_string(),
+                ContractError::referral(*msg).to_string()
+            );
+        }
+        suite.participate_referred(BUYER, id, 500, None, "ref").unwrap();
+        suite.participate_from(OTHER, id, 300).unwrap();
+        assert_eq!(
+            suite.referral_rewards(REFERRER).pending,
+            vec![PendingReferralReward {
+                id,
+                amount: Uint128::new(10),
+            }]
+        );
+
+        // 80 of fee on the 800 raised, 10 for the referred 500 and 8 for the sale referrer
+        suite.advance(1_000);
+        for sender in [OWNER, BUYER, OTHER].iter() {
+            suite
+                .app
+                .execute_contract(Addr::unchecked(*sender), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+                .unwrap();
+        }
+        assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), owner_payout);
+        assert_eq!(suite.native_balance(&Addr::unchecked(COLLECTOR)), fee_payout);
+        assert_eq!(
+            suite.referral_rewards(REFERRER),
+            ReferralRewardsResponse {
+                rewards: vec![ReferralReward {
+                    info: cw_asset::AssetInfo::native(DENOM),
+                    accrued: Uint128::new(10),
+                    claimed: Uint128::zero(),
+                }],
+                pending: vec![],
+            }
+        );
+
+        for (referrer, reward) in [(REFERRER, 10), ("promoter", 8)].iter() {
+            let claim = ExecuteMsg::ClaimReferralRewards {};
+            suite
+                .app
+                .execute_contract(Addr::unchecked(*referrer), suite.launchpad.clone(), &claim, &[])
+                .unwrap();
+            assert_eq!(suite.native_balance(&Addr::unchecked(*referrer)), *reward);
+
+            let err = suite
+                .app
+                .execute_contract(Addr::unchecked(*referrer), suite.launchpad.clone(), &claim, &[])
+                .unwrap_err();
+            assert_eq!(
+                err.downcast::<ContractError>().unwrap().to_string(),
+                ContractError::referral("No referral reward to claim").to_string()
+            );
+        }
+        assert_eq!(suite.native_balance(&suite.launchpad), 0);
+    }
+}
+
+#[test]
+fn batch_auction_referrals_follow_each_final_allocation() {
+    let mut suite = Suite::with_config(
+        0,
+        0,
+        ReferralConfig {
+            sale_percentage: Uint128::zero(),
+            participant_percentage: Uint128::new(1_000),
+            base: ReferralBase::Raised,
+        },
+    );
+    suite
+        .app
+        .send_tokens(Addr::unchecked(BUYER), Addr::unchecked(ADMIN), &coins(1_000, DENOM))
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(REFERRER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::RegisterReferralCode {
+                code: "ref".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    let param = SellParam {
+        token_sale_amt: Uint128::new(1_000),
+        sale_type: SaleType::BatchAuction {
+            reserve_price: Decimal::percent(10),
+        },
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    // the winning and the losing bid are both referred
+    suite
+        .participate_referred(BUYER, id, 400, Some(Decimal::percent(80)), "ref")
+        .unwrap();
+    suite.bid_from(OTHER, id, 300, Decimal::percent(50)).unwrap();
+    suite
+        .participate_referred(ADMIN, id, 100, Some(Decimal::percent(20)), "ref")
+        .unwrap();
+    assert_eq!(
+        suite.referral_rewards(REFERRER).pending,
+        vec![PendingReferralReward {
+            id,
+            amount: Uint128::new(50),
+        }]
+    );
+
+    suite.advance(1_000);
+    suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &ExecuteMsg::SettleAuction { id }, &[])
+        .unwrap();
+
+    // only the filled 400 earns a reward, not a share of the 800 committed
+    suite
+        .app
+        .execute_contract(Addr::unchecked(BUYER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    suite
+        .app
+        .execute_contract(Addr::unchecked(ADMIN), suite.launchpad.clone(), &ExecuteMsg::RefundMsg { id }, &[])
+        .unwrap();
+    let rewards = suite.referral_rewards(REFERRER);
+    assert_eq!(rewards.pending, vec![]);
+    assert_eq!(
+        rewards.rewards,
+        vec![ReferralReward {
+            info: cw_asset::AssetInfo::native(DENOM),
+            accrued: Uint128::new(40),
+            claimed: Uint128::zero(),
+        }]
+    );
+
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OTHER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(REFERRER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::ClaimReferralRewards {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(REFERRER)), 40);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 460);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
+
+#[test]
+fn oversubscribed_referrals_stay_within_the_validated_share() {
+    let mut suite = Suite::with_config(
+        1_000,
+        0,
+        ReferralConfig {
+            sale_percentage: Uint128::zero(),
+            participant_percentage: Uint128::new(9_000),
+            base: ReferralBase::Raised,
+        },
+    );
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(REFERRER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::RegisterReferralCode {
+                code: "ref".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    let param = SellParam {
+        sale_type: SaleType::Overflow,
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+
+    // 32 referred participants commit 3_232 for the 1_000 hard cap, each paying
+    // a rounded up share of the raise
+    let participants: Vec<String> = (0..32).map(|i| format!("participant{}", i)).collect();
+    for participant in &participants {
+        suite
+            .app
+            .send_tokens(Addr::unchecked(BUYER), Addr::unchecked(participant), &coins(101, DENOM))
+            .unwrap();
+        suite.participate_referred(participant, id, 101, None, "ref").unwrap();
+    }
+    suite.advance(1_000);
+
+    // the fee and the 90% referral share take the whole raise, no rounding allowance on top
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert_eq!(suite.native_balance(&Addr::unchecked(COLLECTOR)), 100);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 0);
+
+    for participant in &participants {
+        suite
+            .app
+            .execute_contract(Addr::unchecked(participant), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+            .unwrap();
+    }
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(REFERRER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::ClaimReferralRewards {},
+            &[],
+        )
+        .unwrap();
+
+    // each paid 32 earning 28, the 4 held back but unused go to the owner
+    for participant in &participants {
+        assert_eq!(suite.native_balance(&Addr::unchecked(participant)), 69);
+    }
+    assert_eq!(suite.native_balance(&Addr::unchecked(REFERRER)), 896);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 4);
+    // the rounding dust paid over the raise stays in the launchpad
+    assert_eq!(suite.native_balance(&suite.launchpad), 24);
+}
+
+#[test]
+fn lottery_winners_are_drawn_from_a_revealed_seed() {
+    use sha2::{Digest, Sha256};
+
+    const OPERATOR: &str = "operator";
+    const THIRD: &str = "third";
+
+    let mut suite = Suite::new();
+    suite
+        .app
+        .send_tokens(Addr::unchecked(BUYER), Addr::unchecked(THIRD), &coins(1_000, DENOM))
+        .unwrap();
+    let base = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let param = SellParam {
+        lottery: Some(LotteryParam {
+            operator: OPERATOR.to_string(),
+            registration_end: base.start - 5,
+            winners: 2,
+            allocation: Uint128::new(300),
+        }),
+        ..base
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let execute = |suite: &mut Suite, sender: &str, msg: ExecuteMsg| {
+        suite
+            .app
+            .execute_contract(Addr::unchecked(sender), suite.launchpad.clone(), &msg, &[])
+            .map_err(|err| err.downcast::<ContractError>().unwrap().to_string())
+    };
+
+    let seed = "launch day".to_string();
+    let commitment = hex::encode(Sha256::digest(seed.as_bytes()));
+    execute(&mut suite, OPERATOR, ExecuteMsg::CommitSeed { id, commitment }).unwrap();
+    for address in [BUYER, OTHER, THIRD] {
+        execute(&mut suite, address, ExecuteMsg::RegisterTicket { id }).unwrap();
+    }
+    assert_eq!(
+        execute(&mut suite, OPERATOR, ExecuteMsg::RegisterTicket { id }).unwrap_err(),
+        ContractError::lottery("Lottery operator cannot register").to_string()
+    );
+    assert_eq!(
+        execute(&mut suite, BUYER, ExecuteMsg::RegisterTicket { id }).unwrap_err(),
+        ContractError::lottery("Ticket already registered").to_string()
+    );
+
+    suite.advance(6);
+    assert_eq!(
+        execute(&mut suite, THIRD, ExecuteMsg::RegisterTicket { id }).unwrap_err(),
+        ContractError::lottery("Registration is closed").to_string()
+    );
+    assert_eq!(
+        execute(&mut suite, OPERATOR, ExecuteMsg::RevealSeed { id, seed: "other day".to_string() }).unwrap_err(),
+        ContractError::lottery("Seed does not match the commitment").to_string()
+    );
+    execute(&mut suite, OPERATOR, ExecuteMsg::RevealSeed { id, seed: seed.clone() }).unwrap();
+
+    // the draw seed mixes in the tickets and the reveal block
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    let block = suite.app.block_info();
+    let draw_seed: [u8; 32] = Sha256::new()
+        .chain_update(seed.as_bytes())
+        .chain_update(sale.progress.tickets_hash.as_slice())
+        .chain_update(block.height.to_be_bytes())
+        .chain_update(block.time.nanos().to_be_bytes())
+        .finalize()
+        .into();
+    assert_eq!(sale.progress.seed, Some(Binary::from(draw_seed)));
+
+    // nobody takes part before every winner is drawn
+    suite.advance(10);
+    let err = suite.participate_from(BUYER, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::lottery("Lottery winners are not drawn yet").to_string()
+    );
+    execute(&mut suite, ADMIN, ExecuteMsg::DrawWinners { id }).unwrap();
+
+    let is_winner = |suite: &Suite, address: &str| -> IsWinnerResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::IsWinner {
+                    id,
+                    address: Addr::unchecked(address),
+                },
+            )
+            .unwrap()
+    };
+    let (winners, losers): (Vec<_>, Vec<_>) =
+        [BUYER, OTHER, THIRD].iter().partition(|address| is_winner(&suite, address).is_winner);
+    assert_eq!(winners.len(), 2);
+    assert!(is_winner(&suite, BUYER).is_drawn);
+
+    // only winners take part, up to the lottery allocation
+    suite.participate_from(winners[0], id, 300).unwrap();
+    let err = suite.participate_from(winners[1], id, 301).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::AboveMaxContribution(Uint128::new(300)).to_string()
+    );
+    let err = suite.participate_from(losers[0], id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::lottery("Only lottery winners can participate").to_string()
+    );
+}
+
+#[test]
+fn lottery_sale_is_cancelled_when_the_seed_misses_the_start() {
+    use sha2::{Digest, Sha256};
+
+    const OPERATOR: &str = "operator";
+
+    let mut suite = Suite::new();
+    let base = suite.sell_param(AssetInfoUnchecked::native(DENOM));
+    let param = SellParam {
+        lottery: Some(LotteryParam {
+            operator: OPERATOR.to_string(),
+            registration_end: base.start - 5,
+            winners: 2,
+            allocation: Uint128::new(300),
+        }),
+        ..base
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let execute = |suite: &mut Suite, sender: &str, msg: ExecuteMsg| {
+        suite
+            .app
+            .execute_contract(Addr::unchecked(sender), suite.launchpad.clone(), &msg, &[])
+            .map_err(|err| err.downcast::<ContractError>().unwrap().to_string())
+    };
+
+    let seed = "launch day".to_string();
+    let commitment = hex::encode(Sha256::digest(seed.as_bytes()));
+    execute(&mut suite, OPERATOR, ExecuteMsg::CommitSeed { id, commitment }).unwrap();
+    execute(&mut suite, BUYER, ExecuteMsg::RegisterTicket { id }).unwrap();
+
+    // the operator stays silent until the sale starts
+    suite.advance(10);
+    assert_eq!(
+        execute(&mut suite, OPERATOR, ExecuteMsg::RevealSeed { id, seed }).unwrap_err(),
+        ContractError::lottery("Reveal deadline has passed").to_string()
+    );
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    assert_eq!(sale.status, crate::state::SaleStatus::Cancelled);
+    let err = suite.participate_from(BUYER, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::Cancelled.to_string()
+    );
+
+    // the owner takes the escrow back without anybody cancelling
+    execute(&mut suite, OWNER, ExecuteMsg::RefundMsg { id }).unwrap();
+    assert_eq!(suite.cw20_balance(&sale.sale.token_addr, &Addr::unchecked(OWNER)), 2_000);
+}
+
+#[test]
+fn guaranteed_round_caps_wallets_by_tier_weight() {
+    const THIRD: &str = "third";
+
+    let mut suite = Suite::new();
+    suite
+        .app
+        .send_tokens(Addr::unchecked(BUYER), Addr::unchecked(THIRD), &coins(1_000, DENOM))
+        .unwrap();
+    let now = suite.now();
+    let param = SellParam {
+        rounds: vec![
+            Round {
+                guaranteed: true,
+                ..round(now + 10, now + 310, false)
+            },
+            round(now + 400, now + 1_000, false),
+        ],
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let create = |suite: &mut Suite| {
+        suite.app.execute_contract(
+            Addr::unchecked(OWNER),
+            suite.launchpad.clone(),
+            &ExecuteMsg::CreatePresaleMsg {
+                amount: Uint128::zero(),
+                param: param.clone(),
+            },
+            &[],
+        )
+    };
+    let err = create(&mut suite).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::sell("Guaranteed rounds need staking tiers to be configured").to_string()
+    );
+
+    let staking_code_id = suite.app.store_code(mock_staking::contract());
+    let staking = suite
+        .app
+        .instantiate_contract(staking_code_id, Addr::unchecked(ADMIN), &Empty {}, &[], "staking", None)
+        .unwrap();
+    for (address, amount) in [(BUYER, 1_000), (OTHER, 100), (THIRD, 10)] {
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(ADMIN),
+                staking.clone(),
+                &mock_staking::ExecuteMsg::SetStake {
+                    address: address.to_string(),
+                    amount: Uint128::new(amount),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+    let tiers = vec![
+        Tier {
+            min_staked: Uint128::new(100),
+            weight: 1,
+        },
+        Tier {
+            min_staked: Uint128::new(1_000),
+            weight: 3,
+        },
+    ];
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            suite.launchpad.clone(),
+            &ExecuteMsg::UpdateTiers {
+                staking_contract: staking.to_string(),
+                tiers: tiers.clone(),
+            },
+            &[],
+        )
+        .unwrap();
+    let res: TiersResponse = suite.app.wrap().query_wasm_smart(&suite.launchpad, &QueryMsg::Tiers {}).unwrap();
+    assert_eq!(
+        res.config,
+        Some(TierConfig {
+            staking_contract: staking,
+            tiers
+        })
+    );
+
+    let id = sale_id(&create(&mut suite).unwrap());
+    let register = |suite: &mut Suite, sender: &str| {
+        suite
+            .app
+            .execute_contract(Addr::unchecked(sender), suite.launchpad.clone(), &ExecuteMsg::RegisterTier { id }, &[])
+            .map_err(|err| err.downcast::<ContractError>().unwrap().to_string())
+    };
+    register(&mut suite, BUYER).unwrap();
+    register(&mut suite, OTHER).unwrap();
+    assert_eq!(
+        register(&mut suite, THIRD).unwrap_err(),
+        ContractError::tier("Staked amount 10 is below the lowest tier").to_string()
+    );
+
+    // weights 3 and 1 share the hard cap of 1_000
+    let res: TierRegistrationResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.launchpad,
+            &QueryMsg::TierRegistration {
+                id,
+                address: Addr::unchecked(BUYER),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.weight, 3);
+    assert_eq!(res.cap, Uint128::new(750));
+
+    suite.advance(10);
+    assert_eq!(
+        register(&mut suite, THIRD).unwrap_err(),
+        ContractError::tier("Registration is closed").to_string()
+    );
+    let err = suite.participate_from(OTHER, id, 251).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::AboveMaxContribution(Uint128::new(250)).to_string()
+    );
+    suite.participate_from(OTHER, id, 250).unwrap();
+    let err = suite.participate_from(THIRD, id, 100).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::tier("Address is not registered for the guaranteed round").to_string()
+    );
+    suite.participate_from(BUYER, id, 750).unwrap();
+}
+
+#[test]
+fn tier_registration_ignores_stake_from_the_same_block() {
+    let mut suite = Suite::new();
+    let cw20_code_id = suite.app.store_code(cw20_contract());
+    let platform_token = suite
+        .app
+        .instantiate_contract(
+            cw20_code_id,
+            Addr::unchecked(ADMIN),
+            &Cw20InstantiateMsg {
+                name: "Platform".to_string(),
+                symbol: "PLAT".to_string(),
+                decimals: 6,
+                initial_balances: vec![
+                    Cw20Coin {
+                        address: BUYER.to_string(),
+                        amount: Uint128::new(1_000),
+                    },
+                    Cw20Coin {
+                        address: OTHER.to_string(),
+                        amount: Uint128::new(1_000),
+                    },
+                ],
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "platform",
+            None,
+        )
+        .unwrap();
+    let staking_code_id = suite.app.store_code(staking_contract());
+    let staking = suite
+        .app
+        .instantiate_contract(
+            staking_code_id,
+            Addr::unchecked(ADMIN),
+            &absolute_fi_staking::msg::InstantiateMsg {
+                token: platform_token.to_string(),
+                unbonding_period: 100,
+            },
+            &[],
+            "staking",
+            None,
+        )
+        .unwrap();
+    let stake = |suite: &mut Suite, sender: &str, amount: u128| {
+        suite
+            .app
+            .execute_contract(
+                Addr::unchecked(sender),
+                platform_token.clone(),
+                &Cw20ExecuteMsg::Send {
+                    contract: staking.to_string(),
+                    amount: Uint128::new(amount),
+                    msg: to_json_binary(&absolute_fi_staking::msg::ReceiveMsg::Stake {}).unwrap(),
+                },
+                &[],
+            )
+            .unwrap();
+    };
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            suite.launchpad.clone(),
+            &ExecuteMsg::UpdateTiers {
+                staking_contract: staking.to_string(),
+                tiers: vec![
+                    Tier {
+                        min_staked: Uint128::new(100),
+                        weight: 1,
+                    },
+                    Tier {
+                        min_staked: Uint128::new(1_000),
+                        weight: 3,
+                    },
+                ],
+            },
+            &[],
+        )
+        .unwrap();
+    let now = suite.now();
+    let param = SellParam {
+        rounds: vec![Round {
+            guaranteed: true,
+            ..round(now + 10, now + 1_000, false)
+        }],
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let register = |suite: &mut Suite, sender: &str| {
+        suite
+            .app
+            .execute_contract(Addr::unchecked(sender), suite.launchpad.clone(), &ExecuteMsg::RegisterTier { id }, &[])
+            .map_err(|err| err.downcast::<ContractError>().unwrap().to_string())
+    };
+    let weight = |suite: &Suite, address: &str| {
+        let res: TierRegistrationResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.launchpad,
+                &QueryMsg::TierRegistration {
+                    id,
+                    address: Addr::unchecked(address),
+                },
+            )
+            .unwrap();
+        res.weight
+    };
+
+    stake(&mut suite, BUYER, 1_000);
+    suite.app.update_block(|block| block.height += 1);
+
+    // a stake flashed in the registering block is not part of the snapshot
+    stake(&mut suite, OTHER, 1_000);
+    assert_eq!(
+        register(&mut suite, OTHER).unwrap_err(),
+        ContractError::tier("Staked amount 0 is below the lowest tier").to_string()
+    );
+    register(&mut suite, BUYER).unwrap();
+    assert_eq!(weight(&suite, BUYER), 3);
+
+    suite.app.update_block(|block| block.height += 1);
+    register(&mut suite, OTHER).unwrap();
+    assert_eq!(weight(&suite, OTHER), 3);
+}
+
+#[test]
+fn successful_sale_provides_dex_liquidity_on_settlement() {
+    let mut suite = Suite::new();
+    let pair_code_id = suite.app.store_code(mock_pair::contract());
+    let pair = suite
+        .app
+        .instantiate_contract(pair_code_id, Addr::unchecked(ADMIN), &Empty {}, &[], "pair", None)
+        .unwrap();
+    let param = SellParam {
+        liquidity: Some(LiquidityParam {
+            percentage: Uint128::new(5_000),
+            token_amount: Uint128::new(1_000),
+            pair: pair.to_string(),
+            listing_price: Decimal::percent(50),
+        }),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    // the liquidity reserve is escrowed along with the sale amount
+    assert_eq!(sale.progress.token_escrowed, Uint128::new(3_000));
+
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 400).unwrap();
+    suite.advance(1_000);
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+
+    // half of the 400 raised is paired with 400 tokens at the listing price
+    let token = sale.sale.token_addr;
+    assert_eq!(suite.native_balance(&pair), 200);
+    assert_eq!(suite.cw20_balance(&token, &pair), 400);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 200);
+    assert_eq!(suite.cw20_balance(&token, &Addr::unchecked(OWNER)), 1_800);
+
+    let escrow: EscrowResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Escrow { id })
+        .unwrap();
+    assert!(escrow.is_solvent);
+    assert_eq!(escrow.outstanding, Uint128::new(800));
+}
+
+#[test]
+fn failed_liquidity_provision_pays_the_reserve_to_the_owner() {
+    // no pair was created at this address, providing to it fails
+    const MISSING_PAIR: &str = "missing_pair";
+
+    let mut suite = Suite::new();
+    let param = SellParam {
+        liquidity: Some(LiquidityParam {
+            percentage: Uint128::new(5_000),
+            token_amount: Uint128::new(1_000),
+            pair: MISSING_PAIR.to_string(),
+            listing_price: Decimal::percent(50),
+        }),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 400).unwrap();
+    suite.advance(1_000);
+
+    // the settlement goes through, the 200 and 400 tokens paired go to the owner instead
+    let res = suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "provide_liquidity_failed")));
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    let token = sale.sale.token_addr;
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 400);
+    assert_eq!(suite.cw20_balance(&token, &Addr::unchecked(OWNER)), 2_200);
+    assert_eq!(sale.progress.cur_liquidity, Uint128::zero());
+    assert_eq!(sale.progress.token_liquidity, Uint128::zero());
+
+    // the pair keeps no allowance over the launchpad tokens
+    let allowance: AllowanceResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &token,
+            &Cw20QueryMsg::Allowance {
+                owner: suite.launchpad.to_string(),
+                spender: MISSING_PAIR.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(allowance.allowance, Uint128::zero());
+
+    let escrow: EscrowResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Escrow { id })
+        .unwrap();
+    assert!(escrow.is_solvent);
+    assert_eq!(escrow.outstanding, Uint128::new(800));
+}
+
+#[test]
+fn liquidity_is_skipped_when_a_side_rounds_to_zero() {
+    let mut suite = Suite::new();
+    let pair_code_id = suite.app.store_code(mock_pair::contract());
+    let pair = suite
+        .app
+        .instantiate_contract(pair_code_id, Addr::unchecked(ADMIN), &Empty {}, &[], "pair", None)
+        .unwrap();
+    let param = SellParam {
+        liquidity: Some(LiquidityParam {
+            percentage: Uint128::new(5_000),
+            token_amount: Uint128::new(1_000),
+            pair: pair.to_string(),
+            listing_price: Decimal::from_ratio(300u128, 1u128),
+        }),
+        ..suite.sell_param(AssetInfoUnchecked::native(DENOM))
+    };
+    let id = sale_id(
+        &suite
+            .app
+            .execute_contract(
+                Addr::unchecked(OWNER),
+                suite.launchpad.clone(),
+                &ExecuteMsg::CreatePresaleMsg {
+                    amount: Uint128::zero(),
+                    param,
+                },
+                &[],
+            )
+            .unwrap(),
+    );
+    suite.advance(10);
+    suite.participate_from(BUYER, id, 400).unwrap();
+    suite.advance(1_000);
+    suite
+        .app
+        .execute_contract(Addr::unchecked(OWNER), suite.launchpad.clone(), &ExecuteMsg::ClaimMsg { id }, &[])
+        .unwrap();
+
+    // 200 currency would not buy a single token at the listing price, the owner gets the whole raise
+    let sale: SaleResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.launchpad, &QueryMsg::Sale { id })
+        .unwrap();
+    let token = sale.sale.token_addr;
+    assert_eq!(sale.progress.cur_liquidity, Uint128::zero());
+    assert_eq!(sale.progress.token_liquidity, Uint128::zero());
+    assert_eq!(suite.native_balance(&pair), 0);
+    assert_eq!(suite.cw20_balance(&token, &pair), 0);
+    assert_eq!(suite.native_balance(&Addr::unchecked(OWNER)), 400);
+    assert_eq!(suite.cw20_balance(&token, &Addr::unchecked(OWNER)), 2_200);
+    assert_eq!(suite.native_balance(&suite.launchpad), 0);
+}
@@ -1,6 +1,7 @@
 pub mod contract;
-mod error;
-// pub mod integration_tests;
+pub mod error;
+#[cfg(test)]
+mod integration_tests;
 pub mod msg;
 pub mod state;
 pub mod handler;
@@ -1,28 +1,34 @@
-use cosmwasm_std::{Addr, Uint128};
-use cw_asset::{Asset};
+use cosmwasm_std::{Addr, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw_asset::{Asset, AssetInfoUnchecked};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{SellParam, Sale, SaleProgress, SaleStatus,};
+use crate::state::{MerkleProof, ReferralConfig, Tier, TierConfig, SignedVoucher, ReferralReward, SellParam, Sale, SaleProgress, SaleStatus,};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub min_cap: [Uint128; 2],
     pub min_token_sale_amt: Uint128,
     pub token_code_id: u64,
     pub fee_percentage: Uint128,
+    pub token_fee_percentage: Uint128,
+    pub fee_collector: String,
+    pub referral: ReferralConfig,
 }
 
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     UpdateConfigMsg{
-        min_cap: [Uint128; 2],
         min_token_sale_amt: Uint128,
         token_code_id: u64,
         fee_percentage: Uint128,
+        token_fee_percentage: Uint128,
+        fee_collector: String,
+        referral: ReferralConfig,
     },
     CreatePresaleMsg{
         amount: Uint128,
@@ -32,17 +38,101 @@ pub enum ExecuteMsg {
         id: u64,
         cur: Asset,
         allow_partial: bool,
+        referral_code: Option<String>,
+        merkle_proof: Option<MerkleProof>,
+        voucher: Option<SignedVoucher>,
+        /// fails the purchase when fewer tokens would be bought, ignored by overflow sales
+        min_tokens_out: Option<Uint128>,
+        /// bid max price in currency per token, batch auctions only
+        max_price: Option<Decimal>,
     },
     ClaimMsg{
         id: u64,
     },
     RefundMsg{
         id: u64,
-    }
+    },
+    CancelSale{
+        id: u64,
+        reason: String,
+    },
+    AddToWhitelist{
+        id: u64,
+        round: u32,
+        addresses: Vec<String>,
+    },
+    RemoveFromWhitelist{
+        id: u64,
+        round: u32,
+        addresses: Vec<String>,
+    },
+    UpdateMerkleRoot{
+        id: u64,
+        merkle_root: Option<String>,
+    },
+    RegisterReferralCode{
+        code: String,
+    },
+    ClaimReferralRewards{},
+    SettleAuction{
+        id: u64,
+    },
+    RegisterTicket{
+        id: u64,
+    },
+    /// hex encoded sha256 of the seed revealed later
+    CommitSeed{
+        id: u64,
+        commitment: String,
+    },
+    RevealSeed{
+        id: u64,
+        seed: String,
+    },
+    DrawWinners{
+        id: u64,
+    },
+    UpdateTiers{
+        staking_contract: String,
+        tiers: Vec<Tier>,
+    },
+    /// snapshots the sender stake into a tier weight for the guaranteed rounds
+    RegisterTier{
+        id: u64,
+    },
+    AddCurrency{
+        info: AssetInfoUnchecked,
+        min_soft_cap: Uint128,
+    },
+    RemoveCurrency{
+        info: AssetInfoUnchecked,
+    },
+    Receive(Cw20ReceiveMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum ReceiveMsg {
+    ParticipateHook{
+        id: u64,
+        allow_partial: bool,
+        referral_code: Option<String>,
+        merkle_proof: Option<MerkleProof>,
+        voucher: Option<SignedVoucher>,
+        /// fails the purchase when fewer tokens would be bought, ignored by overflow sales
+        min_tokens_out: Option<Uint128>,
+        /// bid max price in currency per token, batch auctions only
+        max_price: Option<Decimal>,
+    },
+    CreatePresale{
+        param: SellParam,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum QueryMsg {
     Admin {},
     Config {},
@@ -64,6 +154,103 @@ pub enum QueryMsg {
         id: u64,
         address: Addr,
     },
+    Vesting {
+        id: u64,
+        address: Addr,
+    },
+    ReferralCode {
+        code: String,
+    },
+    ReferralRewards {
+        address: Addr,
+    },
+    Escrow {
+        id: u64,
+    },
+    Whitelist {
+        id: u64,
+        round: u32,
+        start_after: Option<String>,
+        limit: Option<u64>,
+    },
+    VerifyMerkleProof {
+        id: u64,
+        address: Addr,
+        proof: MerkleProof,
+    },
+    ContributionLimit {
+        id: u64,
+        address: Addr,
+    },
+    ValidateSellParam {
+        /// address which would create the sale
+        owner: Addr,
+        /// existing cw20 sold, none when the sale mints its token
+        token: Option<Addr>,
+        param: SellParam,
+    },
+    CurrencyWhitelist {},
+    Allocation {
+        id: u64,
+        address: Addr,
+    },
+    CurrentPrice {
+        id: u64,
+    },
+    SimulateBuy {
+        id: u64,
+        cur_amount: Uint128,
+    },
+    Currency {
+        info: AssetInfoUnchecked,
+    },
+    IsWinner {
+        id: u64,
+        address: Addr,
+    },
+    Tiers {},
+    TierRegistration {
+        id: u64,
+        address: Addr,
+    },
+}
+
+/// Astroport/Terraswap style pair interface the sale liquidity is provided through.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairExecuteMsg {
+    ProvideLiquidity {
+        assets: Vec<PairAsset>,
+        slippage_tolerance: Option<Decimal>,
+        /// receiver of the LP tokens
+        receiver: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairAsset {
+    pub info: PairAssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairAssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TiersResponse {
+    pub config: Option<TierConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TierRegistrationResponse {
+    /// zero when not registered
+    pub weight: u32,
+    /// currency guaranteed to the address at the current registrations
+    pub cap: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -72,6 +259,19 @@ pub struct CurrencyWhitelistResponse {
     pub native: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CurrencyResponse {
+    /// minimum soft cap of sales quoted in the currency, none when not whitelisted
+    pub min_soft_cap: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsWinnerResponse {
+    pub is_winner: bool,
+    /// whether every lottery winner is drawn
+    pub is_drawn: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct WhitelistResponse {
     pub addresses: Vec<Addr>,
@@ -82,6 +282,8 @@ pub struct SaleResponse {
     pub sale: Sale,
     pub progress: SaleProgress,
     pub status: SaleStatus,
+    pub owner_vested: Uint128,
+    pub owner_claimable: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -91,3 +293,88 @@ pub struct SalesResponse {
 
 
 
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingResponse {
+    pub total: Uint128,
+    pub vested: Uint128,
+    pub claimed: Uint128,
+    pub claimable: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralCodeResponse {
+    pub referrer: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingReferralReward {
+    pub id: u64,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralRewardsResponse {
+    pub rewards: Vec<ReferralReward>,
+    pub pending: Vec<PendingReferralReward>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowResponse {
+    /// sale tokens escrowed at creation
+    pub escrowed: Uint128,
+    /// sale tokens already sent out of the escrow
+    pub released: Uint128,
+    /// sale tokens still owed to participants, owner and fee collector
+    pub outstanding: Uint128,
+    /// launchpad balance of the sale token
+    pub balance: Uint128,
+    pub is_solvent: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleProofResponse {
+    pub is_valid: bool,
+    /// allocation left for the address, zero when the proof is invalid
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContributionLimitResponse {
+    /// least total contribution accepted from the address
+    pub min: Option<Uint128>,
+    /// currency the address may still contribute in the open round
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidateSellParamResponse {
+    pub is_valid: bool,
+    /// first failed check, none when valid
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllocationResponse {
+    pub committed: Uint128,
+    /// tokens allocated at the current subscription, final once claimed
+    pub token_amount: Uint128,
+    /// committed currency returned on claim
+    pub cur_refund: Uint128,
+    /// total commitments over the hard cap
+    pub oversubscription: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CurrentPriceResponse {
+    /// currency per token, the clearing price of an auction once sold out
+    pub price: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateBuyResponse {
+    pub token_amount: Uint128,
+    /// part of the amount spent, the rest is returned
+    pub cur_spent: Uint128,
+    pub average_price: Decimal,
+}
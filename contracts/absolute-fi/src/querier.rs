@@ -1,24 +1,40 @@
-use cosmwasm_std::{Addr, Deps, Env, Order, StdResult};
-use cw_storage_plus::{Bound, PrimaryKey};
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdError, StdResult, Uint128};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw_storage_plus::Bound;
+use cw_asset::AssetInfoUnchecked;
+use std::convert::TryFrom;
 
 use crate::{
-  state::{ PRESALE, PRESALE_PROGRESS,PRESALE_PARTICIPANT_BY_PRESALE_ID, SaleProgressPersonal, SALE_OWNER }, msg::{SaleResponse, SalesResponse}
+  state::{ currency_min_soft_cap, CURRENCY_NATIVE, CURRENCY_TOKEN, PRESALE, PRESALE_PROGRESS,PRESALE_PARTICIPANT_BY_PRESALE_ID, PRESALE_WL, LOTTERY_WINNER, TIER_CONFIG, TIER_WEIGHT, REFERRAL_CODE, REFERRAL_PENDING, REFERRAL_REWARD, TOKEN_ADDRESS_BY_PRESALE_ID, MerkleProof, Sale, SellParam, SaleProgress, SaleProgressPersonal, SaleStatus, SaleType, SALE_OWNER },
+  msg::{AllocationResponse, SimulateBuyResponse, ContributionLimitResponse, CurrentPriceResponse, CurrencyResponse, CurrencyWhitelistResponse, IsWinnerResponse, TierRegistrationResponse, TiersResponse, ValidateSellParamResponse, EscrowResponse, MerkleProofResponse, PendingReferralReward, ReferralCodeResponse, ReferralRewardsResponse, SaleResponse, SalesResponse, VestingResponse, WhitelistResponse}
 };
 
 const DEF_ITER_LIMIT: u64 = 30;
 const DEF_WL_LIMIT: u64 = 100;
 
+fn sale_response(sale: Sale, progress: SaleProgress, now: u64) -> SaleResponse {
+    let status = sale.status(&progress, now);
+    let owner_vested = sale.owner_vested(now);
+    let owner_claimable = match status {
+        SaleStatus::Ended | SaleStatus::Filled => owner_vested - progress.owner_token_claimed,
+        _ => Uint128::zero(),
+    };
+
+    SaleResponse {
+        sale,
+        progress,
+        status,
+        owner_vested,
+        owner_claimable,
+    }
+}
+
 
 pub fn query_sale(deps: Deps, env: Env, id: u64) -> StdResult<SaleResponse> {
     let sale = PRESALE.load(deps.storage, id)?;
     let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
-    let status = sale.status(&progress, env.block.time.seconds());
 
-    Ok(SaleResponse {
-        sale,
-        progress,
-        status,
-    })
+    Ok(sale_response(sale, progress, env.block.time.seconds()))
 }
 
 pub fn query_sales(
@@ -49,16 +65,8 @@ pub fn query_sales(
 
     let sales = ss
         .into_iter()
-        .zip(progs.into_iter())
-        .map(|(sale, progress)| {
-            let status = sale.status(&progress, now);
-
-            SaleResponse {
-                sale,
-                progress,
-                status,
-            }
-        })
+        .zip(progs)
+        .map(|(sale, progress)| sale_response(sale, progress, now))
         .collect::<Vec<_>>();
 
     Ok(SalesResponse { sales })
@@ -86,13 +94,8 @@ pub fn query_sales_owner(
             let id = e.unwrap();
             let sale = PRESALE.load(deps.storage, id).unwrap();
             let progress = PRESALE_PROGRESS.load(deps.storage, id).unwrap();
-            let status = sale.status(&progress, now);
 
-            SaleResponse {
-                sale,
-                progress,
-                status,
-            }
+            sale_response(sale, progress, now)
         })
         .take(limit.unwrap_or(DEF_ITER_LIMIT) as usize)
         .collect::<Vec<_>>();
@@ -105,3 +108,320 @@ pub fn query_progress(deps: Deps, id: u64, address: Addr) -> StdResult<SaleProgr
         .may_load(deps.storage, (&address, id))?
         .unwrap_or_default())
 }
+
+pub fn query_vesting(deps: Deps, env: Env, id: u64, address: Addr) -> StdResult<VestingResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let sale_prog = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let progress = PRESALE_PARTICIPANT_BY_PRESALE_ID
+        .may_load(deps.storage, (&address, id))?
+        .unwrap_or_default();
+
+    let total = match progress.is_settled {
+        true => progress.token_got,
+        false => sale.allocation(&sale_prog, &address, &progress, env.block.time.seconds()).0,
+    };
+    let vested = sale.token_vested(total, env.block.time.seconds());
+
+    Ok(VestingResponse {
+        total,
+        vested,
+        claimed: progress.token_claimed,
+        claimable: vested - progress.token_claimed,
+    })
+}
+
+pub fn query_referral_code(deps: Deps, code: String) -> StdResult<ReferralCodeResponse> {
+    Ok(ReferralCodeResponse {
+        referrer: REFERRAL_CODE.may_load(deps.storage, &code)?,
+    })
+}
+
+pub fn query_referral_rewards(deps: Deps, address: Addr) -> StdResult<ReferralRewardsResponse> {
+    let rewards = REFERRAL_REWARD
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|e| e.map(|(_, reward)| reward))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let pending = REFERRAL_PENDING
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|e| e.map(|(id, amount)| PendingReferralReward { id, amount }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ReferralRewardsResponse { rewards, pending })
+}
+
+pub fn query_escrow(deps: Deps, env: Env, id: u64) -> StdResult<EscrowResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+
+    let released = progress.token_claimed
+        + progress.owner_token_claimed
+        + progress.token_fee
+        + progress.token_excess
+        + progress.token_liquidity;
+    let held = progress.token_escrowed - released;
+    let outstanding = sale.token_outstanding(&progress, env.block.time.seconds());
+
+    let balance = match TOKEN_ADDRESS_BY_PRESALE_ID.may_load(deps.storage, id)? {
+        Some(token) => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(
+                token,
+                &Cw20QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            res.balance
+        }
+        None => Uint128::zero(),
+    };
+
+    Ok(EscrowResponse {
+        escrowed: progress.token_escrowed,
+        released,
+        outstanding,
+        balance,
+        is_solvent: held >= outstanding && balance >= held,
+    })
+}
+
+pub fn query_whitelist(
+    deps: Deps,
+    id: u64,
+    round: u32,
+    start_after: Option<String>,
+    limit: Option<u64>,
+) -> StdResult<WhitelistResponse> {
+    let start_after = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+
+    let addresses = PRESALE_WL
+        .prefix((id, round))
+        .keys(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit.unwrap_or(DEF_WL_LIMIT) as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(WhitelistResponse { addresses })
+}
+
+pub fn query_verify_merkle_proof(
+    deps: Deps,
+    id: u64,
+    address: Addr,
+    proof: MerkleProof,
+) -> StdResult<MerkleProofResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let is_valid = sale.verify_merkle_proof(&address, &proof).unwrap_or(false);
+    let remaining = match is_valid {
+        true => {
+            let progress = PRESALE_PARTICIPANT_BY_PRESALE_ID
+                .may_load(deps.storage, (&address, id))?
+                .unwrap_or_default();
+            proof.max_allocation.saturating_sub(progress.cur_spent)
+        }
+        false => Uint128::zero(),
+    };
+
+    Ok(MerkleProofResponse { is_valid, remaining })
+}
+
+pub fn query_contribution_limit(
+    deps: Deps,
+    env: Env,
+    id: u64,
+    address: Addr,
+) -> StdResult<ContributionLimitResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let personal = PRESALE_PARTICIPANT_BY_PRESALE_ID
+        .may_load(deps.storage, (&address, id))?
+        .unwrap_or_default();
+    let now = env.block.time.seconds();
+
+    let is_open = matches!(sale.status(&progress, now), SaleStatus::Ongoing { round: Some(_) });
+    let is_full = personal.cur_spent.is_zero()
+        && sale.max_participants.is_some_and(|max| progress.participants >= max);
+
+    let remaining = match (is_open && !is_full, sale.active_round(now)) {
+        (true, Some((_, round))) => {
+            let sale_left = match &sale.sale_type {
+                SaleType::FixedPrice | SaleType::DutchAuction { .. } => {
+                    let token_left = sale.token_sale_amt - progress.token_sold;
+                    (sale.raise_cap() - progress.cur_raised).min(sale.cur_amount(token_left, sale.price(&round, now)))
+                }
+                SaleType::BondingCurve { curve } => {
+                    (sale.raise_cap() - progress.cur_raised)
+                        .min(curve.cost(progress.token_sold, sale.token_sale_amt, sale.token_decimals)?)
+                }
+                SaleType::Overflow | SaleType::BatchAuction { .. } => Uint128::MAX,
+            };
+            let tier_cap = match round.guaranteed {
+                true => {
+                    let weight = TIER_WEIGHT.may_load(deps.storage, (id, &address))?.unwrap_or_default();
+                    Some(sale.tier_cap(&progress, weight))
+                }
+                false => None,
+            };
+            match sale.max_contribution(&round).into_iter().chain(tier_cap).min() {
+                Some(cap) => cap.saturating_sub(personal.cur_spent).min(sale_left),
+                None => sale_left,
+            }
+        }
+        _ => Uint128::zero(),
+    };
+
+    Ok(ContributionLimitResponse {
+        min: sale.min_cur_alloc_per,
+        remaining,
+    })
+}
+
+pub fn query_validate_sell_param(
+    deps: Deps,
+    env: Env,
+    owner: Addr,
+    token: Option<Addr>,
+    param: SellParam,
+) -> StdResult<ValidateSellParamResponse> {
+    // an existing token brings its own metadata, as on creation through `Receive`
+    let param = match &token {
+        Some(token) => {
+            let token_info: TokenInfoResponse = deps.querier.query_wasm_smart(token, &Cw20QueryMsg::TokenInfo {})?;
+            SellParam {
+                token_name: token_info.name,
+                token_symbol: token_info.symbol,
+                token_decimals: token_info.decimals,
+                ..param
+            }
+        }
+        None => param,
+    };
+    let error = param
+        .validate(deps, &owner, token.is_none(), env.block.time.seconds())
+        .err()
+        .map(|err| err.to_string());
+
+    Ok(ValidateSellParamResponse {
+        is_valid: error.is_none(),
+        error,
+    })
+}
+
+pub fn query_currency_whitelist(deps: Deps) -> StdResult<CurrencyWhitelistResponse> {
+    let token = CURRENCY_TOKEN
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let native = CURRENCY_NATIVE
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CurrencyWhitelistResponse { token, native })
+}
+
+pub fn query_currency(deps: Deps, info: AssetInfoUnchecked) -> StdResult<CurrencyResponse> {
+    let info = info.check(deps.api, None)?;
+
+    Ok(CurrencyResponse {
+        min_soft_cap: currency_min_soft_cap(deps.storage, &info)?,
+    })
+}
+
+pub fn query_is_winner(deps: Deps, id: u64, address: Addr) -> StdResult<IsWinnerResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+
+    Ok(IsWinnerResponse {
+        is_winner: LOTTERY_WINNER.has(deps.storage, (id, &address)),
+        is_drawn: sale.lottery.is_some_and(|lottery| lottery.is_drawn(&progress)),
+    })
+}
+
+pub fn query_tiers(deps: Deps) -> StdResult<TiersResponse> {
+    Ok(TiersResponse {
+        config: TIER_CONFIG.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_tier_registration(deps: Deps, id: u64, address: Addr) -> StdResult<TierRegistrationResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let weight = TIER_WEIGHT.may_load(deps.storage, (id, &address))?.unwrap_or_default();
+
+    Ok(TierRegistrationResponse {
+        weight,
+        cap: sale.tier_cap(&progress, weight),
+    })
+}
+
+pub fn query_allocation(deps: Deps, env: Env, id: u64, address: Addr) -> StdResult<AllocationResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let personal = PRESALE_PARTICIPANT_BY_PRESALE_ID
+        .may_load(deps.storage, (&address, id))?
+        .unwrap_or_default();
+
+    let (token_amount, cur_refund) = match personal.is_settled {
+        true => (personal.token_got, personal.cur_returned),
+        false => sale.allocation(&progress, &address, &personal, env.block.time.seconds()),
+    };
+
+    Ok(AllocationResponse {
+        committed: personal.cur_spent,
+        token_amount,
+        cur_refund,
+        oversubscription: Decimal::from_ratio(progress.cur_raised + progress.cur_excess, sale.raise_cap()),
+    })
+}
+
+pub fn query_current_price(deps: Deps, env: Env, id: u64) -> StdResult<CurrentPriceResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let now = env.block.time.seconds();
+
+    let price = match (&sale.sale_type, sale.active_round(now)) {
+        (SaleType::DutchAuction { .. }, _) => sale.clearing_price(&progress, now),
+        (SaleType::BatchAuction { reserve_price }, _) => match progress.is_auction_settled {
+            true => progress.clearing_price,
+            false => *reserve_price,
+        },
+        (SaleType::BondingCurve { curve }, _) => Decimal::try_from(curve.spot_price(progress.token_sold, sale.token_decimals))
+            .map_err(|_| StdError::generic_err("Curve price out of range"))?,
+        (_, Some((_, round))) => {
+            let (token, cur) = sale.price(&round, now);
+            Decimal::from_ratio(cur, token)
+        }
+        (_, None) => sale.auction_price(now),
+    };
+
+    Ok(CurrentPriceResponse { price })
+}
+
+pub fn query_simulate_buy(deps: Deps, env: Env, id: u64, cur_amount: Uint128) -> StdResult<SimulateBuyResponse> {
+    let sale = PRESALE.load(deps.storage, id)?;
+    let progress = PRESALE_PROGRESS.load(deps.storage, id)?;
+    let now = env.block.time.seconds();
+
+    // outside of a round the quote is made at the first round
+    let round = sale
+        .active_round(now)
+        .or_else(|| sale.active_round(sale.rounds.first().map_or(sale.start, |round| round.start)))
+        .map(|(_, round)| round)
+        .ok_or_else(|| StdError::generic_err("Sale has no round"))?;
+    let (token_amount, cur_spent) = sale.quote(&progress, &round, cur_amount, now)?;
+
+    Ok(SimulateBuyResponse {
+        token_amount,
+        cur_spent,
+        average_price: match token_amount.is_zero() {
+            true => Decimal::zero(),
+            false => Decimal::from_ratio(cur_spent, token_amount),
+        },
+    })
+}
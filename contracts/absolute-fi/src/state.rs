@@ -1,39 +1,177 @@
-use cosmwasm_std::{Addr, Api, Uint128, StdResult};
+use cosmwasm_std::{Addr, Api, Binary, Decimal, Decimal256, Deps, Fraction, Uint128, StdResult, Storage};
+use std::convert::TryFrom;
 use crate::ContractError;
 use cw_controllers::Admin;
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw_asset::{AssetInfo, AssetInfoBase, AssetInfoUnchecked};
+use cw20::{EmbeddedLogo, Logo};
+use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use sha2::{Digest, Sha256};
+use cw_asset::{AssetInfo, AssetInfoUnchecked};
 
 
 //SALE STATE
 
+// longest a sale may run, 90 days
+pub const MAX_SALE_DURATION: u64 = 90 * 24 * 60 * 60;
+// cw20-base limit on embedded logos
+const LOGO_SIZE_CAP: usize = 5 * 1024;
+
 // global config
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const ADMIN: Admin = Admin::new("admin");
 
-pub const OWNER_CACHE: Item<Addr> = Item::new("owner_cache");
+// currencies sales may be quoted in, with their minimum soft cap
+pub const CURRENCY_NATIVE: Map<&str, Uint128> = Map::new("currency_native");
+pub const CURRENCY_TOKEN: Map<&Addr, Uint128> = Map::new("currency_token");
+
+// staking tiers granting guaranteed allocations
+pub const TIER_CONFIG: Item<TierConfig> = Item::new("tier_config");
+
+/// Minimum soft cap of a whitelisted currency, `None` when not whitelisted.
+pub fn currency_min_soft_cap(storage: &dyn Storage, info: &AssetInfo) -> StdResult<Option<Uint128>> {
+    match info {
+        AssetInfo::Native(denom) => CURRENCY_NATIVE.may_load(storage, denom),
+        AssetInfo::Cw20(addr) => CURRENCY_TOKEN.may_load(storage, addr),
+        _ => Ok(None),
+    }
+}
+
+// token instantiations waiting for their reply, reply id -> sale id
+pub const REPLY_SEQ: Item<u64> = Item::new("reply_seq");
+pub const PENDING_INSTANTIATE: Map<u64, u64> = Map::new("pending_instantiate");
+// liquidity provisions waiting for their reply, reply id -> sale id
+pub const PENDING_LIQUIDITY: Map<u64, u64> = Map::new("pending_liquidity");
 
 // sale related state
 pub const PRESALE_ID: Item<u64> = Item::new("presale_id");
 pub const PRESALE: Map<u64, Sale> = Map::new("presale");
 pub const PRESALE_PROGRESS: Map<u64, SaleProgress> = Map::new("presale_progress");
 pub const PRESALE_PARTICIPANT_BY_PRESALE_ID: Map<(&Addr, u64), SaleProgressPersonal> = Map::new("sale_progress_personal");
-pub const PRESALE_WL: Map<(u64, &Addr), ()> = Map::new("sale_wl");
+// allowlist per sale round
+pub const PRESALE_WL: Map<(u64, u32, &Addr), ()> = Map::new("sale_wl");
+// voucher nonces already used, per sale
+pub const VOUCHER_NONCE: Map<(u64, u64), ()> = Map::new("voucher_nonce");
+// batch auction bids ordered by max price, (sale id, price atomics, bidder) -> currency amount
+pub const AUCTION_BID: Map<(u64, u128, &Addr), Uint128> = Map::new("auction_bid");
+// lottery tickets by index, shuffled in place by the draw
+pub const LOTTERY_TICKET: Map<(u64, u32), Addr> = Map::new("lottery_ticket");
+pub const LOTTERY_ENTRANT: Map<(u64, &Addr), ()> = Map::new("lottery_entrant");
+pub const LOTTERY_WINNER: Map<(u64, &Addr), ()> = Map::new("lottery_winner");
+// tier weight of the addresses registered for the guaranteed rounds of a sale
+pub const TIER_WEIGHT: Map<(u64, &Addr), u32> = Map::new("tier_weight");
 
 pub const TOKEN_ADDRESS_BY_PRESALE_ID: Map<u64, Addr> = Map::new("ta_pi");
 
+// referral related state
+pub const REFERRAL_CODE: Map<&str, Addr> = Map::new("referral_code");
+// rewards on the currency committed by referred participants, pending their final allocation
+pub const REFERRAL_PENDING: Map<(&Addr, u64), Uint128> = Map::new("referral_pending");
+// referrer of each referred participant of a sale
+pub const REFERRAL_OF: Map<(u64, &Addr), Addr> = Map::new("referral_of");
+// rewards of a referrer keyed by currency
+pub const REFERRAL_REWARD: Map<(&Addr, &str), ReferralReward> = Map::new("referral_reward");
+
 // indexing helper for sale
 pub const SALE_OWNER: Map<(&Addr, u64), ()> = Map::new("sale_owner");
 
 
+// fee percentages are expressed in basis points
+pub const FEE_DENOMINATOR: Uint128 = Uint128::new(10_000);
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    pub min_cap: [Uint128; 2],
     pub min_token_sale_amt: Uint128,
     pub token_code_id: u64,
     pub fee_percentage: Uint128,
+    pub token_fee_percentage: Uint128,
+    pub fee_collector: Addr,
+    pub referral: ReferralConfig,
+}
+
+impl Config {
+    pub fn assert_valid_fees(&self) -> Result<(), ContractError> {
+        for fee in [self.fee_percentage, self.token_fee_percentage] {
+            (fee <= FEE_DENOMINATOR)
+                .then_some(())
+                .ok_or(ContractError::InvalidFeePercentage(FEE_DENOMINATOR))?;
+        }
+
+        // referral rewards are carved out of the fee or of the owner's share of the raise
+        let referral_total = self.referral.sale_percentage + self.referral.participant_percentage;
+        let referral_max = match self.referral.base {
+            ReferralBase::PlatformFee => FEE_DENOMINATOR,
+            ReferralBase::Raised => FEE_DENOMINATOR - self.fee_percentage,
+        };
+        (referral_total <= referral_max)
+            .then_some(())
+            .ok_or(ContractError::InvalidFeePercentage(referral_max))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TierConfig {
+    /// `absolute-fi-staking` contract answering the `StakedAt` snapshots
+    pub staking_contract: Addr,
+    /// ordered by increasing minimum stake
+    pub tiers: Vec<Tier>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Tier {
+    pub min_staked: Uint128,
+    pub weight: u32,
+}
+
+impl TierConfig {
+    pub fn assert_valid(&self) -> Result<(), ContractError> {
+        let mut prev_min = None;
+        for tier in &self.tiers {
+            (prev_min.is_none_or(|min| tier.min_staked > min) && tier.weight > 0)
+                .then_some(())
+                .ok_or_else(|| {
+                    ContractError::tier("Tiers must be ordered by minimum stake and have a non-zero weight")
+                })?;
+            prev_min = Some(tier.min_staked);
+        }
+
+        Ok(())
+    }
+
+    /// Weight of the highest tier reached by `staked`, zero below every tier.
+    pub fn weight(&self, staked: Uint128) -> u32 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| staked >= tier.min_staked)
+            .map_or(0, |tier| tier.weight)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralConfig {
+    /// share of the base paid to the referrer of a successful sale
+    pub sale_percentage: Uint128,
+    /// share of the base paid to the referrer of each contribution
+    pub participant_percentage: Uint128,
+    pub base: ReferralBase,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferralBase {
+    PlatformFee,
+    Raised,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralReward {
+    pub info: AssetInfo,
+    pub accrued: Uint128,
+    pub claimed: Uint128,
 }
 
 
@@ -47,44 +185,647 @@ pub struct SellParam {
     pub token_sale_amt: Uint128,
     // --
     pub cur_info: AssetInfoUnchecked,
+    pub sale_type: SaleType,
     pub soft_cap: Uint128,
     pub hard_cap: Uint128,
     pub max_cur_alloc_per: Option<Uint128>,
+    pub min_cur_alloc_per: Option<Uint128>,
+    pub min_participants: Option<u32>,
+    pub max_participants: Option<u32>,
     pub owner_allocation: Uint128,
     pub token_name: String,
     pub token_symbol: String,
+    pub token_decimals: u8,
     pub token_project: String,
     pub token_description: String,
-    pub token_marketing: String,
-    pub token_logo: String,
+    pub token_marketing: Option<String>,
+    pub token_logo: Option<Logo>,
     // --
-    pub wl_end_time: Option<u64>,
+    pub rounds: Vec<Round>,
+    pub merkle_root: Option<String>,
+    pub voucher_signer: Option<Binary>,
+    // --
+    pub vesting: Option<VestingSchedule>,
+    pub owner_vesting: Option<VestingSchedule>,
+    pub lottery: Option<LotteryParam>,
+    pub liquidity: Option<LiquidityParam>,
 }
 
 impl SellParam {
-    // pub fn assert_valid_wl(&self) -> Result<(), ContractError> {
-    //     if let Some(wl) = self.wl_end_time {
-    //         (wl > self.start && wl < self.end)
-    //             .then(|| ())
-    //             .ok_or_else(|| {
-    //                 ContractError::sell("Whitelist end time must be between start and end")
-    //             })?;
-    //     }
-
-    //     Ok(())
-    // }
+    /// Runs every check a new sale must pass, short of the token inventory.
+    /// `mints_token` is set when the sale token is instantiated from the param
+    /// metadata, rather than an existing cw20 being sold.
+    pub fn validate(&self, deps: Deps, owner: &Addr, mints_token: bool, now: u64) -> Result<(), ContractError> {
+        let config = CONFIG.load(deps.storage)?;
+        let cur_info = self.cur_info.check(deps.api, None)?;
+        for addr in self
+            .referrer
+            .iter()
+            .chain(self.token_marketing.iter())
+            .chain(self.lottery.iter().map(|lottery| &lottery.operator))
+            .chain(self.liquidity.iter().map(|liquidity| &liquidity.pair))
+        {
+            deps.api.addr_validate(addr)?;
+        }
+        // the owner would take a cut of the fee charged on their own raise
+        (self.referrer.as_deref() != Some(owner.as_str()))
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Sale referrer cannot be the sale owner"))?;
+        let min_soft_cap = currency_min_soft_cap(deps.storage, &cur_info)?
+            .ok_or_else(|| ContractError::sell(format!("Currency {} is not whitelisted", cur_info)))?;
+
+        self.assert_start_end(now)?;
+        self.assert_valid_amounts(&config, min_soft_cap)?;
+        self.assert_valid_sale_type()?;
+        self.assert_valid_rounds()?;
+        self.assert_valid_caps()?;
+        self.assert_valid_wl()?;
+        self.assert_valid_vesting()?;
+        if mints_token {
+            self.assert_valid_token_metadata()?;
+        }
+        self.assert_valid_lottery(now)?;
+        self.assert_valid_liquidity(&config)?;
+
+        if self.rounds.iter().any(|round| round.guaranteed) {
+            TIER_CONFIG
+                .may_load(deps.storage)?
+                .ok_or_else(|| ContractError::sell("Guaranteed rounds need staking tiers to be configured"))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn assert_valid_amounts(&self, config: &Config, min_soft_cap: Uint128) -> Result<(), ContractError> {
+        (!self.token_sale_amt.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Token sale amount cannot be zero"))?;
+        (self.token_sale_amt >= config.min_token_sale_amt)
+            .then_some(())
+            .ok_or_else(|| {
+                ContractError::sell(format!("Token sale amount must be gteq than {}", config.min_token_sale_amt))
+            })?;
+
+        (self.soft_cap >= min_soft_cap)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell(format!("Soft cap must be gteq than {}", min_soft_cap)))?;
+        (!self.hard_cap.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Hard cap cannot be zero"))?;
+        (self.soft_cap <= self.hard_cap)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Soft cap must be lteq than hard cap"))?;
+
+        // the owner allocation is carved out of the hard cap, buyers raise the rest
+        (self.owner_allocation < self.hard_cap)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Owner allocation must be less than hard cap"))?;
+        // a ratio out of the decimal range, e.g. with 18 decimals tokens, is far from zero
+        Decimal::checked_from_ratio(self.token_sale_amt, self.hard_cap - self.owner_allocation)
+            .map_or(true, |price| !price.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Token price rounds to zero, raise token sale amount or lower hard cap"))?;
+
+        Ok(())
+    }
+
+    pub fn assert_valid_sale_type(&self) -> Result<(), ContractError> {
+        if let SaleType::DutchAuction { start_price, floor_price, decay } = &self.sale_type {
+            (!floor_price.is_zero() && floor_price < start_price)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Floor price must be non-zero and less than start price"))?;
+            (self.token_sale_amt.mul_ceil(*start_price) <= self.hard_cap - self.owner_allocation)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Hard cap must cover the token sale amount at start price"))?;
+            if let PriceDecay::Stepwise { interval } = decay {
+                (*interval > 0 && *interval <= self.end - self.start)
+                    .then_some(())
+                    .ok_or_else(|| ContractError::sell("Price step interval must be within the sale duration"))?;
+            }
+        }
+        if let SaleType::BatchAuction { reserve_price } = &self.sale_type {
+            (!reserve_price.is_zero())
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Reserve price cannot be zero"))?;
+        }
+        if let SaleType::BondingCurve { curve } = &self.sale_type {
+            curve.assert_valid(self.token_sale_amt, self.token_decimals)?;
+            (curve.cost(Uint128::zero(), self.token_sale_amt, self.token_decimals)? <= self.hard_cap - self.owner_allocation)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Hard cap must cover the whole curve"))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn assert_valid_rounds(&self) -> Result<(), ContractError> {
+        let mut prev_end = self.start;
+        for round in &self.rounds {
+            (round.start >= prev_end && round.end > round.start)
+                .then_some(())
+                .ok_or_else(|| {
+                    ContractError::sell("Rounds must be ordered and must not overlap")
+                })?;
+            (round.price != Some(Decimal::zero()))
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Round price cannot be zero"))?;
+            (round.price.is_none() || self.sale_type == SaleType::FixedPrice)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Round prices are only supported by fixed price sales"))?;
+            prev_end = round.end;
+        }
+        (prev_end <= self.end)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Rounds must end before the sale end"))?;
+
+        Ok(())
+    }
+
+    pub fn assert_valid_caps(&self) -> Result<(), ContractError> {
+        if let (Some(min), Some(max)) = (self.min_cur_alloc_per, self.max_cur_alloc_per) {
+            (min <= max)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Minimum contribution must be lteq than maximum contribution"))?;
+        }
+        if let (Some(min), Some(max)) = (self.min_participants, self.max_participants) {
+            (min <= max)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Minimum participants must be lteq than maximum participants"))?;
+        }
+        (self.max_participants != Some(0))
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Maximum participants cannot be zero"))?;
+
+        Ok(())
+    }
+
+    pub fn assert_valid_wl(&self) -> Result<(), ContractError> {
+        if let Some(root) = &self.merkle_root {
+            assert_valid_merkle_root(root)?;
+        }
+        if let Some(signer) = &self.voucher_signer {
+            // compressed or uncompressed secp256k1 public key
+            (signer.len() == 33 || signer.len() == 65)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Invalid voucher signer public key"))?;
+        }
+
+        Ok(())
+    }
 
     pub fn assert_start_end(&self, now: u64) -> Result<(), ContractError> {
         (self.end > self.start)
-            .then(|| ())
+            .then_some(())
             .ok_or_else(|| ContractError::sell("Invalid end date, must be after start"))?;
+        (self.end - self.start <= MAX_SALE_DURATION)
+            .then_some(())
+            .ok_or_else(|| {
+                ContractError::sell(format!("Sale duration must be lteq than {} seconds", MAX_SALE_DURATION))
+            })?;
 
         (self.start >= now)
-            .then(|| ())
+            .then_some(())
             .ok_or_else(|| ContractError::sell("Invalid start date, cannot be in the past"))?;
 
         Ok(())
     }
+
+    pub fn assert_valid_vesting(&self) -> Result<(), ContractError> {
+        if let Some(vesting) = &self.vesting {
+            vesting.assert_valid()?;
+        }
+        if let Some(vesting) = &self.owner_vesting {
+            vesting.assert_valid()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn assert_valid_liquidity(&self, config: &Config) -> Result<(), ContractError> {
+        let liquidity = match &self.liquidity {
+            Some(liquidity) => liquidity,
+            None => return Ok(()),
+        };
+
+        // the paired currency comes out of the owner share of the raise
+        let owner_share = match config.referral.base {
+            ReferralBase::PlatformFee => FEE_DENOMINATOR - config.fee_percentage,
+            ReferralBase::Raised => {
+                FEE_DENOMINATOR
+                    - config.fee_percentage
+                    - config.referral.sale_percentage
+                    - config.referral.participant_percentage
+            }
+        };
+        (!liquidity.percentage.is_zero() && liquidity.percentage <= owner_share)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell(format!("Liquidity percentage must be between 1 and {}", owner_share)))?;
+        (!liquidity.token_amount.is_zero() && !liquidity.listing_price.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Liquidity token amount and listing price cannot be zero"))?;
+
+        Ok(())
+    }
+
+    /// Checks the new token metadata against the cw20-base rules, so that a bad
+    /// parameter fails the creation up front rather than in the submessage.
+    pub fn assert_valid_token_metadata(&self) -> Result<(), ContractError> {
+        if let Some(logo) = &self.token_logo {
+            assert_valid_logo(logo)?;
+        }
+
+        Cw20InstantiateMsg {
+            name: self.token_name.clone(),
+            symbol: self.token_symbol.clone(),
+            decimals: self.token_decimals,
+            initial_balances: vec![],
+            mint: None,
+            marketing: None,
+        }
+        .validate()
+        .map_err(|e| ContractError::sell(e.to_string()))
+    }
+
+    pub fn assert_valid_lottery(&self, now: u64) -> Result<(), ContractError> {
+        let lottery = match &self.lottery {
+            Some(lottery) => lottery,
+            None => return Ok(()),
+        };
+
+        (self.sale_type == SaleType::FixedPrice)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Lottery is only supported by fixed price sales"))?;
+        // the seed is revealed between the registration end and the sale start
+        (lottery.registration_end > now && lottery.registration_end < self.start)
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Lottery registration must end between now and the sale start"))?;
+        (lottery.winners > 0 && !lottery.allocation.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Lottery winners and allocation cannot be zero"))?;
+
+        Ok(())
+    }
+}
+
+pub fn assert_valid_merkle_root(root: &str) -> Result<(), ContractError> {
+    let mut root_buf = [0u8; 32];
+    hex::decode_to_slice(root, &mut root_buf)
+        .map_err(|_| ContractError::sell("Merkle root must be a hex encoded sha256 hash"))
+}
+
+fn assert_valid_logo(logo: &Logo) -> Result<(), ContractError> {
+    const XML_PREFIX: &[u8] = b"<?xml ";
+    const XML_POSTFIX: &[u8] = b"?>";
+    const PNG_HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    match logo {
+        Logo::Url(url) => (!url.is_empty())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Logo url cannot be empty")),
+        Logo::Embedded(EmbeddedLogo::Svg(svg)) => {
+            (svg.len() <= LOGO_SIZE_CAP)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Logo binary data exceeds 5KB limit"))?;
+
+            let preamble = svg.split_inclusive(|c| *c == b'>').next().unwrap_or_default();
+            (preamble.starts_with(XML_PREFIX) && preamble.ends_with(XML_POSTFIX))
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Invalid xml preamble for SVG"))
+        }
+        Logo::Embedded(EmbeddedLogo::Png(png)) => {
+            (png.len() <= LOGO_SIZE_CAP)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Logo binary data exceeds 5KB limit"))?;
+
+            png.starts_with(&PNG_HEADER)
+                .then_some(())
+                .ok_or_else(|| ContractError::sell("Invalid png header"))
+        }
+    }
+}
+
+/// Allowlist entry `(address, max_allocation)` with its proof against the sale root.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MerkleProof {
+    pub max_allocation: Uint128,
+    pub proof: Vec<String>,
+}
+
+/// DEX liquidity provided with part of the raise once a sale succeeds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LiquidityParam {
+    /// share of the raised currency paired, in basis points
+    pub percentage: Uint128,
+    /// sale tokens escrowed for the pair on top of the sale amount, the unused part goes back to the owner
+    pub token_amount: Uint128,
+    /// Astroport/Terraswap style pair of the sale token and currency. Pairs are not
+    /// created through a factory, it must already exist when the sale settles; if
+    /// providing to it fails, the reserved currency and tokens go to the owner
+    pub pair: String,
+    /// currency per token the liquidity is provided at
+    pub listing_price: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Liquidity {
+    pub percentage: Uint128,
+    pub token_amount: Uint128,
+    pub pair: Addr,
+    pub listing_price: Decimal,
+}
+
+impl Liquidity {
+    /// Currency and tokens paired out of `cur_raised` at the listing price,
+    /// within the token reserve. Nothing is paired when either side rounds
+    /// down to zero, so no currency is held back without a matching deposit.
+    pub fn amounts(&self, cur_raised: Uint128) -> (Uint128, Uint128) {
+        let cur_amount = cur_raised.multiply_ratio(self.percentage, FEE_DENOMINATOR);
+        let token_amount =
+            cur_amount.multiply_ratio(self.listing_price.denominator(), self.listing_price.numerator());
+
+        let (cur_amount, token_amount) = match token_amount > self.token_amount {
+            true => (self.token_amount.mul_floor(self.listing_price), self.token_amount),
+            false => (cur_amount, token_amount),
+        };
+        match cur_amount.is_zero() || token_amount.is_zero() {
+            true => (Uint128::zero(), Uint128::zero()),
+            false => (cur_amount, token_amount),
+        }
+    }
+}
+
+/// Ticket lottery drawing the only addresses allowed to take part in the sale.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LotteryParam {
+    /// address committing to and revealing the draw seed, the sale is cancelled
+    /// when the seed is not revealed before the sale start. It cannot register
+    /// and is trusted not to register through other addresses either
+    pub operator: String,
+    /// tickets are registered until then, before the sale start
+    pub registration_end: u64,
+    pub winners: u32,
+    /// currency each winner may contribute
+    pub allocation: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lottery {
+    pub operator: Addr,
+    pub registration_end: u64,
+    pub winners: u32,
+    pub allocation: Uint128,
+}
+
+impl Lottery {
+    /// Whether the seed is revealed and every winner drawn.
+    pub fn is_drawn(&self, progress: &SaleProgress) -> bool {
+        progress.seed.is_some() && progress.tickets_drawn == self.winners.min(progress.tickets)
+    }
+
+    /// Pseudo-random number of draw step `idx`, the seed hashed with the index.
+    pub fn ticket_roll(seed: &Binary, idx: u32) -> u64 {
+        let hash: [u8; 32] = Sha256::new()
+            .chain_update(seed.as_slice())
+            .chain_update(idx.to_be_bytes())
+            .finalize()
+            .into();
+        let mut roll = [0u8; 8];
+        roll.copy_from_slice(&hash[..8]);
+        u64::from_be_bytes(roll)
+    }
+}
+
+/// How the sale inventory is allocated among participants.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleType {
+    /// first come first served at a fixed price until the hard cap
+    FixedPrice,
+    /// commitments may exceed the hard cap, tokens are allocated pro-rata and
+    /// the unused currency is refunded on claim
+    Overflow,
+    /// price in currency per token decays from `start_price` to `floor_price`
+    /// over the sale, everyone pays the clearing price and the difference is
+    /// refunded on claim
+    DutchAuction {
+        start_price: Decimal,
+        floor_price: Decimal,
+        decay: PriceDecay,
+    },
+    /// the price rises with `token_sold` along `curve`, buyers pay its integral
+    BondingCurve { curve: BondingCurve },
+    /// bids of a max price in currency per token and a currency amount, settled
+    /// after the end at a single clearing price selling the whole inventory
+    BatchAuction { reserve_price: Decimal },
+}
+
+/// Price in currency base units per whole token, `10^token_decimals` base units,
+/// as a function of the whole tokens sold.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BondingCurve {
+    /// `base_price + slope * sold`
+    Linear { base_price: Decimal, slope: Decimal },
+    /// `base_price * e^(growth * sold)`
+    Exponential { base_price: Decimal, growth: Decimal },
+}
+
+// largest `growth * supply` of an exponential curve, a price multiplier of about 4.8e8
+const MAX_CURVE_EXPONENT: u64 = 20;
+
+/// Whole tokens in `amount` base units of a `decimals` token.
+fn whole_tokens(amount: Uint128, decimals: u8) -> Decimal256 {
+    Decimal256::from_ratio(amount, Uint128::new(10).pow(decimals.into()))
+}
+
+impl BondingCurve {
+    pub fn assert_valid(&self, supply: Uint128, decimals: u8) -> Result<(), ContractError> {
+        let (base_price, exponent) = match self {
+            BondingCurve::Linear { base_price, .. } => (base_price, Decimal256::zero()),
+            BondingCurve::Exponential { base_price, growth } => (
+                base_price,
+                Decimal256::from(*growth)
+                    .checked_mul(whole_tokens(supply, decimals))
+                    .unwrap_or(Decimal256::MAX),
+            ),
+        };
+
+        (!base_price.is_zero())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Curve base price cannot be zero"))?;
+        (exponent <= Decimal256::from_ratio(MAX_CURVE_EXPONENT, 1u8))
+            .then_some(())
+            .ok_or_else(|| {
+                ContractError::sell(format!("Curve growth times supply must be lteq than {}", MAX_CURVE_EXPONENT))
+            })?;
+
+        Ok(())
+    }
+
+    /// Price per base unit of the next token once `sold` base units are sold.
+    pub fn spot_price(&self, sold: Uint128, decimals: u8) -> Decimal256 {
+        let sold = whole_tokens(sold, decimals);
+        let price = match self {
+            BondingCurve::Linear { base_price, slope } => Decimal256::from(*base_price) + Decimal256::from(*slope) * sold,
+            BondingCurve::Exponential { base_price, growth } => {
+                Decimal256::from(*base_price) * exp(Decimal256::from(*growth) * sold)
+            }
+        };
+
+        price / Decimal256::from_ratio(Uint128::new(10).pow(decimals.into()), 1u8)
+    }
+
+    /// Integral of the price between `from` and `to` base units sold, rounded up.
+    pub fn cost(&self, from: Uint128, to: Uint128, decimals: u8) -> StdResult<Uint128> {
+        let (from, to) = (whole_tokens(from, decimals), whole_tokens(to, decimals));
+        let cost = match self {
+            BondingCurve::Linear { base_price, slope } => {
+                // slope * (to^2 - from^2) / 2, factored so only large supplies overflow
+                let rise = Decimal256::from(*slope)
+                    .checked_mul(to - from)?
+                    .checked_mul(to + from)?
+                    / Decimal256::from_ratio(2u8, 1u8);
+                Decimal256::from(*base_price).checked_mul(to - from)?.checked_add(rise)?
+            }
+            BondingCurve::Exponential { base_price, growth } => {
+                let growth = Decimal256::from(*growth);
+                match growth.is_zero() {
+                    true => Decimal256::from(*base_price).checked_mul(to - from)?,
+                    false => {
+                        Decimal256::from(*base_price).checked_mul(exp(growth * to) - exp(growth * from))? / growth
+                    }
+                }
+            }
+        };
+
+        Ok(Uint128::try_from(cost.to_uint_ceil())?)
+    }
+
+    /// Most tokens, up to `max_tokens`, bought with `cur_amount` once `sold` tokens are sold.
+    pub fn tokens_out(&self, sold: Uint128, max_tokens: Uint128, cur_amount: Uint128, decimals: u8) -> StdResult<Uint128> {
+        let (mut low, mut high) = (Uint128::zero(), max_tokens);
+        while low < high {
+            let mid = low + (high - low + Uint128::one()) / Uint128::new(2);
+            match self.cost(sold, sold + mid, decimals)? <= cur_amount {
+                true => low = mid,
+                false => high = mid - Uint128::one(),
+            }
+        }
+
+        Ok(low)
+    }
+}
+
+/// `e^x` through a Taylor series on `x / 2^k`, squared back `k` times.
+fn exp(x: Decimal256) -> Decimal256 {
+    let half = Decimal256::percent(50);
+    let (mut y, mut k) = (x, 0u32);
+    while y > half {
+        y *= half;
+        k += 1;
+    }
+
+    let (mut sum, mut term) = (Decimal256::one(), Decimal256::one());
+    for i in 1..=24u32 {
+        term = term * y / Decimal256::from_ratio(i, 1u8);
+        if term.is_zero() {
+            break;
+        }
+        sum += term;
+    }
+
+    (0..k).fold(sum, |acc, _| acc * acc)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceDecay {
+    Linear,
+    /// the price drops once every `interval` seconds
+    Stepwise { interval: u64 },
+}
+
+/// Participation window of a sale with its own access rules.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Round {
+    pub start: u64,
+    pub end: u64,
+    /// only buyers in the round allowlist, the merkle root or holding a voucher may join
+    pub whitelisted: bool,
+    /// cap on the total currency spent by a wallet once this round is open
+    pub max_cur_alloc_per: Option<Uint128>,
+    /// tokens per currency unit, the sale price when unset
+    pub price: Option<Decimal>,
+    /// only addresses registered with a staking tier may join, each capped to its tier share of the hard cap
+    pub guaranteed: bool,
+}
+
+/// Allocation granted off-chain to `buyer`, signed by the sale voucher signer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Voucher {
+    pub sale_id: u64,
+    pub buyer: String,
+    pub max_amount: Uint128,
+    pub expiry: u64,
+    pub nonce: u64,
+}
+
+/// Voucher with the secp256k1 signature over the sha256 of its JSON encoding.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedVoucher {
+    pub voucher: Voucher,
+    pub signature: Binary,
+}
+
+/// Release schedule of purchased tokens, counted from the sale end.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingSchedule {
+    /// share of the tokens unlocked as soon as the sale can be claimed
+    pub tge_unlock: Decimal,
+    /// seconds after the sale end before the linear release starts
+    pub cliff: u64,
+    /// seconds over which the rest of the tokens is released after the cliff
+    pub duration: u64,
+    /// optional step in seconds, tokens are then released per completed step only
+    pub interval: Option<u64>,
+}
+
+impl VestingSchedule {
+    pub fn assert_valid(&self) -> Result<(), ContractError> {
+        (self.tge_unlock <= Decimal::one())
+            .then_some(())
+            .ok_or_else(|| ContractError::sell("Vesting TGE unlock cannot exceed 100%"))?;
+
+        if let Some(interval) = self.interval {
+            (interval > 0 && interval <= self.duration)
+                .then_some(())
+                .ok_or_else(|| {
+                    ContractError::sell("Vesting interval must be between 1 and duration")
+                })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn vested(&self, total: Uint128, start: u64, now: u64) -> Uint128 {
+        let unlocked = total * self.tge_unlock;
+        let linear_start = start + self.cliff;
+
+        if now < linear_start {
+            return unlocked;
+        }
+
+        let mut elapsed = now - linear_start;
+        if let Some(interval) = self.interval {
+            elapsed -= elapsed % interval;
+        }
+
+        if elapsed >= self.duration {
+            return total;
+        }
+
+        unlocked + (total - unlocked).multiply_ratio(elapsed, self.duration)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -102,23 +843,40 @@ pub struct Sale {
     pub token_sale_amt: Uint128,
     // --
     pub cur_info: AssetInfo,
+    pub sale_type: SaleType,
     pub soft_cap: Uint128,
     pub hard_cap: Uint128,
     pub max_cur_alloc_per: Option<Uint128>,
+    pub min_cur_alloc_per: Option<Uint128>,
+    pub min_participants: Option<u32>,
+    pub max_participants: Option<u32>,
     // --
-    pub wl_end_time: Option<u64>,
+    pub rounds: Vec<Round>,
+    pub merkle_root: Option<String>,
+    pub voucher_signer: Option<Binary>,
     pub owner_allocation: Uint128,
     pub token_name: String,
     pub token_symbol: String,
+    pub token_decimals: u8,
     pub token_project: String,
     pub token_description: String,
-    pub token_marketing: String,
-    pub token_logo: String,
+    pub token_marketing: Option<Addr>,
+    pub token_logo: Option<Logo>,
+    // --
+    pub vesting: Option<VestingSchedule>,
+    pub owner_vesting: Option<VestingSchedule>,
+    pub lottery: Option<Lottery>,
+    pub liquidity: Option<Liquidity>,
+    // -- fee rates at sale creation
+    pub fee_percentage: Uint128,
+    pub token_fee_percentage: Uint128,
+    pub referral: ReferralConfig,
 }
 
 impl Sale {
     pub fn from_param(
         api: &dyn Api,
+        config: &Config,
         param: SellParam,
         id: u64,
         now: u64,
@@ -136,72 +894,546 @@ impl Sale {
             owner_allocation: param.owner_allocation,
             token_name: param.token_name,
             token_symbol: param.token_symbol,
+            token_decimals: param.token_decimals,
             token_project: param.token_project,
             token_description: param.token_description,
-            token_marketing: param.token_marketing,
+            token_marketing: param.token_marketing.map(|m| api.addr_validate(&m)).transpose()?,
             token_logo: param.token_logo,
             token_sale_amt: param.token_sale_amt,
             cur_info: param.cur_info.check(api, None)?,
+            sale_type: param.sale_type,
             soft_cap: param.soft_cap,
             hard_cap: param.hard_cap,
             max_cur_alloc_per: param.max_cur_alloc_per,
-            wl_end_time: param.wl_end_time,
+            min_cur_alloc_per: param.min_cur_alloc_per,
+            min_participants: param.min_participants,
+            max_participants: param.max_participants,
+            rounds: param.rounds,
+            merkle_root: param.merkle_root,
+            voucher_signer: param.voucher_signer,
+            vesting: param.vesting,
+            owner_vesting: param.owner_vesting,
+            lottery: param
+                .lottery
+                .map(|lottery| -> StdResult<_> {
+                    Ok(Lottery {
+                        operator: api.addr_validate(&lottery.operator)?,
+                        registration_end: lottery.registration_end,
+                        winners: lottery.winners,
+                        allocation: lottery.allocation,
+                    })
+                })
+                .transpose()?,
+            liquidity: param
+                .liquidity
+                .map(|liquidity| -> StdResult<_> {
+                    Ok(Liquidity {
+                        percentage: liquidity.percentage,
+                        token_amount: liquidity.token_amount,
+                        pair: api.addr_validate(&liquidity.pair)?,
+                        listing_price: liquidity.listing_price,
+                    })
+                })
+                .transpose()?,
+            fee_percentage: config.fee_percentage,
+            token_fee_percentage: config.token_fee_percentage,
+            referral: config.referral.clone(),
         })
     }
 
     pub fn status(&self, progress: &SaleProgress, now: u64) -> SaleStatus {
 
+        if progress.is_cancelled {
+            return SaleStatus::Cancelled;
+        }
+
         if self.start > now {
             return SaleStatus::NotStarted;
         }
 
-        if progress.token_sold == self.token_sale_amt && progress.cur_raised == self.hard_cap {
+        // nobody can take part in a lottery sale whose seed missed the start, anyone may get refunded
+        if self.lottery.is_some() && progress.seed.is_none() {
+            return SaleStatus::Cancelled;
+        }
+
+        let enough_participants = progress.participants >= self.min_participants.unwrap_or_default();
+
+        // either side running out fills the sale: with the owner allocation carved out of
+        // the hard cap, the raise stops short of it, and rounding may leave a few tokens unsold.
+        // commitments and bids are accepted until the end
+        if enough_participants
+            && !matches!(self.sale_type, SaleType::Overflow | SaleType::BatchAuction { .. })
+            && (progress.token_sold >= self.token_sale_amt || progress.cur_raised >= self.raise_cap())
+        {
             return SaleStatus::Filled;
         }
 
-        if now > self.end && progress.cur_raised < self.soft_cap {
+        if now > self.end && (self.raised(progress, now) < self.soft_cap || !enough_participants) {
             return SaleStatus::Failed;
         }
 
         if now >= self.start && self.end >= now {
-            return SaleStatus::Ongoing;
+            return SaleStatus::Ongoing {
+                round: self.active_round(now).map(|(idx, _)| idx),
+            };
         }
 
         SaleStatus::Ended
     }
 
+    /// Tightest per-wallet cap between the sale and `round`.
+    pub fn max_contribution(&self, round: &Round) -> Option<Uint128> {
+        let lottery_cap = self.lottery.as_ref().map(|lottery| lottery.allocation);
+        [self.max_cur_alloc_per, round.max_cur_alloc_per, lottery_cap]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+    }
+
+    /// Start of the first guaranteed round, when tier registrations close.
+    pub fn guaranteed_start(&self) -> Option<u64> {
+        self.rounds.iter().find(|round| round.guaranteed).map(|round| round.start)
+    }
+
+    /// Share of the raise guaranteed to a tier `weight`.
+    pub fn tier_cap(&self, progress: &SaleProgress, weight: u32) -> Uint128 {
+        match progress.tier_weight_total {
+            0 => Uint128::zero(),
+            total => self.raise_cap().multiply_ratio(weight, total),
+        }
+    }
+
+    /// Number of rounds, a sale without explicit rounds has a single public one.
+    pub fn round_count(&self) -> usize {
+        self.rounds.len().max(1)
+    }
+
+    /// Round open at `now` with its index.
+    pub fn active_round(&self, now: u64) -> Option<(u32, Round)> {
+        if self.rounds.is_empty() {
+            return (self.start <= now && now <= self.end).then_some((
+                0,
+                Round {
+                    start: self.start,
+                    end: self.end,
+                    whitelisted: false,
+                    max_cur_alloc_per: None,
+                    price: None,
+                    guaranteed: false,
+                },
+            ));
+        }
+
+        self.rounds
+            .iter()
+            .enumerate()
+            .find(|(_, round)| round.start <= now && now < round.end)
+            .map(|(idx, round)| (idx as u32, round.clone()))
+    }
+
+    /// Price in `round` at `now` as the ratio of tokens to currency. Both sides are
+    /// in base units, so the ratio holds whatever the decimals of either asset.
+    pub fn price(&self, round: &Round, now: u64) -> (Uint128, Uint128) {
+        if let SaleType::DutchAuction { .. } = self.sale_type {
+            let price = self.auction_price(now);
+            return (price.denominator(), price.numerator());
+        }
+
+        match round.price {
+            Some(price) => (price.numerator(), price.denominator()),
+            None => (self.token_sale_amt, self.raise_cap()),
+        }
+    }
+
+    /// Dutch auction price in currency per token at `now`.
+    pub fn auction_price(&self, now: u64) -> Decimal {
+        let (start_price, floor_price, decay) = match &self.sale_type {
+            SaleType::DutchAuction { start_price, floor_price, decay } => (*start_price, *floor_price, decay),
+            _ => return Decimal::from_ratio(self.raise_cap(), self.token_sale_amt),
+        };
+
+        let duration = self.end - self.start;
+        let elapsed = now.clamp(self.start, self.end) - self.start;
+        let elapsed = match decay {
+            PriceDecay::Linear => elapsed,
+            PriceDecay::Stepwise { interval } => elapsed - elapsed % interval,
+        };
+
+        start_price - (start_price - floor_price) * Decimal::from_ratio(elapsed, duration)
+    }
+
+    /// Price every auction participant pays: the price of the last purchase once
+    /// the inventory is sold out, otherwise the price reached at `now`.
+    pub fn clearing_price(&self, progress: &SaleProgress, now: u64) -> Decimal {
+        match progress.token_sold >= self.token_sale_amt {
+            true => progress.clearing_price,
+            false => self.auction_price(now),
+        }
+    }
+
+    /// Currency the sale raised, auctions only keep tokens sold at the clearing price.
+    pub fn raised(&self, progress: &SaleProgress, now: u64) -> Uint128 {
+        match (&self.sale_type, progress.is_excess_sent) {
+            (SaleType::DutchAuction { .. }, false) => {
+                progress.token_sold.mul_floor(self.clearing_price(progress, now))
+            }
+            _ => progress.cur_raised,
+        }
+    }
+
+    /// Currency buyers can raise, the owner allocation being carved out of the hard cap.
+    pub fn raise_cap(&self) -> Uint128 {
+        self.hard_cap - self.owner_allocation
+    }
+
+    /// Tokens bought with `cur_amount` at `price`.
+    pub fn token_amount(&self, cur_amount: Uint128, price: (Uint128, Uint128)) -> Uint128 {
+        cur_amount.multiply_ratio(price.0, price.1)
+    }
+
+    /// Least currency buying `token_amount` tokens at `price`.
+    pub fn cur_amount(&self, token_amount: Uint128, price: (Uint128, Uint128)) -> Uint128 {
+        let cur_amount = token_amount.multiply_ratio(price.1, price.0);
+        match self.token_amount(cur_amount, price) < token_amount {
+            true => cur_amount + Uint128::one(),
+            false => cur_amount,
+        }
+    }
+
+    /// Tokens bought with `cur_amount` in `round` at `now` and the currency they
+    /// cost, bounded by what is left of the inventory and the hard cap.
+    pub fn quote(
+        &self,
+        progress: &SaleProgress,
+        round: &Round,
+        cur_amount: Uint128,
+        now: u64,
+    ) -> StdResult<(Uint128, Uint128)> {
+        let token_left = self.token_sale_amt - progress.token_sold;
+
+        if let SaleType::BondingCurve { curve } = &self.sale_type {
+            let token_amount = curve.tokens_out(progress.token_sold, token_left, cur_amount, self.token_decimals)?;
+            let cur_spent = curve.cost(progress.token_sold, progress.token_sold + token_amount, self.token_decimals)?;
+            return Ok((token_amount, cur_spent));
+        }
+
+        let price = self.price(round, now);
+        let cur_left = std::cmp::min(
+            self.raise_cap() - progress.cur_raised,
+            self.cur_amount(token_left, price),
+        );
+
+        Ok(match cur_amount > cur_left {
+            true => (std::cmp::min(self.token_amount(cur_left, price), token_left), cur_left),
+            false => (self.token_amount(cur_amount, price), cur_amount),
+        })
+    }
+
+    /// Records a commitment of `cur_amount` to an overflow sale, only the part
+    /// up to the raise cap counts as raised and the rest as `cur_excess`.
+    pub fn commit(&self, progress: &mut SaleProgress, cur_amount: Uint128) {
+        let committed = progress.cur_raised + progress.cur_excess + cur_amount;
+
+        progress.cur_raised = committed.min(self.raise_cap());
+        progress.cur_excess = committed - progress.cur_raised;
+        progress.token_sold = self.token_amount(progress.cur_raised, (self.token_sale_amt, self.raise_cap()));
+    }
+
+    /// Part of `amount` which survives the oversubscription or the clearing price of the sale.
+    pub fn accepted_share(&self, progress: &SaleProgress, amount: Uint128, now: u64) -> Uint128 {
+        let committed = progress.cur_raised + progress.cur_excess;
+        match committed.is_zero() {
+            true => amount,
+            false => amount.multiply_ratio(self.raised(progress, now), committed),
+        }
+    }
+
+    /// Moves the currency given back to auction participants out of `cur_raised`,
+    /// once the sale is over.
+    pub fn settle_raised(&self, progress: &mut SaleProgress, now: u64) {
+        if let SaleType::DutchAuction { .. } = self.sale_type {
+            let committed = progress.cur_raised + progress.cur_excess;
+            progress.cur_raised = self.raised(progress, now);
+            progress.cur_excess = committed - progress.cur_raised;
+        }
+    }
+
+    /// Tokens allocated to a participant and the currency returned to them at
+    /// the current state of the sale.
+    pub fn allocation(
+        &self,
+        progress: &SaleProgress,
+        address: &Addr,
+        personal: &SaleProgressPersonal,
+        now: u64,
+    ) -> (Uint128, Uint128) {
+        match &self.sale_type {
+            SaleType::BatchAuction { .. } => {
+                if !progress.is_auction_settled {
+                    return (Uint128::zero(), Uint128::zero());
+                }
+
+                let clearing_price = progress.clearing_price;
+                match &progress.marginal_bid {
+                    Some((bidder, accepted)) if bidder == address => (
+                        accepted.multiply_ratio(clearing_price.denominator(), clearing_price.numerator()),
+                        personal.cur_spent - accepted,
+                    ),
+                    _ if self.is_winning_bid(progress, personal.bid_price, address) => (
+                        personal
+                            .cur_spent
+                            .multiply_ratio(clearing_price.denominator(), clearing_price.numerator()),
+                        Uint128::zero(),
+                    ),
+                    _ => (Uint128::zero(), personal.cur_spent),
+                }
+            }
+            SaleType::FixedPrice | SaleType::BondingCurve { .. } => (personal.token_got, Uint128::zero()),
+            // rounding up what everybody pays keeps the refunds within the escrow
+            SaleType::DutchAuction { .. } => (
+                personal.token_got,
+                personal.cur_spent - personal.token_got.mul_ceil(self.clearing_price(progress, now)),
+            ),
+            SaleType::Overflow => {
+                let committed = progress.cur_raised + progress.cur_excess;
+                if committed.is_zero() {
+                    return (Uint128::zero(), Uint128::zero());
+                }
+
+                // both round down so the escrow always covers every participant
+                (
+                    personal.cur_spent.multiply_ratio(progress.token_sold, committed),
+                    personal.cur_spent.multiply_ratio(progress.cur_excess, committed),
+                )
+            }
+        }
+    }
+
+    /// Whether a bid is among those filled in full by the auction settlement,
+    /// bids at the same price are filled in address order.
+    pub fn is_winning_bid(&self, progress: &SaleProgress, price: Decimal, address: &Addr) -> bool {
+        match &progress.bid_cursor {
+            Some((cursor_price, cursor_addr)) => {
+                (price.atomics(), address) >= (*cursor_price, cursor_addr)
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the sale outcome still waits for the batch auction settlement.
+    pub fn awaits_settlement(&self, progress: &SaleProgress) -> bool {
+        matches!(self.sale_type, SaleType::BatchAuction { .. }) && !progress.is_auction_settled
+    }
+
     pub fn token_info(&self) -> AssetInfo {
         AssetInfo::cw20(self.token_addr.clone())
     }
 
+    /// Whether `proof` shows `(address, max_allocation)` is a leaf of the sale merkle root.
+    pub fn verify_merkle_proof(&self, address: &Addr, proof: &MerkleProof) -> Result<bool, ContractError> {
+        let root = match &self.merkle_root {
+            Some(root) => root,
+            None => return Ok(false),
+        };
+
+        let leaf = format!("{}{}", address, proof.max_allocation);
+        let mut hash: [u8; 32] = Sha256::digest(leaf.as_bytes()).into();
+
+        for node in &proof.proof {
+            let mut node_buf = [0u8; 32];
+            hex::decode_to_slice(node, &mut node_buf)
+                .map_err(|_| ContractError::whitelist("Invalid merkle proof node"))?;
+
+            // pairs are hashed in sorted order so proofs carry no position
+            let mut pair = [hash, node_buf];
+            pair.sort_unstable();
+            hash = Sha256::digest(pair.concat()).into();
+        }
+
+        let mut root_buf = [0u8; 32];
+        hex::decode_to_slice(root, &mut root_buf)
+            .map_err(|_| ContractError::whitelist("Invalid merkle root"))?;
+
+        Ok(root_buf == hash)
+    }
+
+    /// Amount of `total` purchased tokens released at `now`.
+    pub fn token_vested(&self, total: Uint128, now: u64) -> Uint128 {
+        match &self.vesting {
+            Some(vesting) => vesting.vested(total, self.end, now),
+            None => total,
+        }
+    }
+
+    /// Tokens which must be escrowed for the sale: inventory, owner allocation
+    /// and the largest token fee the sale can be charged.
+    pub fn token_required(&self) -> Uint128 {
+        self.token_sale_amt
+            + self.owner_allocation
+            + self.token_sale_amt.multiply_ratio(self.token_fee_percentage, FEE_DENOMINATOR)
+            + self.liquidity_reserve()
+    }
+
+    /// Sale tokens escrowed for the DEX liquidity.
+    pub fn liquidity_reserve(&self) -> Uint128 {
+        self.liquidity.as_ref().map_or(Uint128::zero(), |liquidity| liquidity.token_amount)
+    }
+
+    /// Tokens still owed from the escrow of the sale.
+    pub fn token_outstanding(&self, progress: &SaleProgress, now: u64) -> Uint128 {
+        match self.status(progress, now) {
+            SaleStatus::Failed | SaleStatus::Cancelled => match progress.is_excess_sent {
+                true => Uint128::zero(),
+                false => progress.token_escrowed,
+            },
+            _ => {
+                let (token_fee, token_liquidity) = match progress.is_excess_sent {
+                    true => (Uint128::zero(), Uint128::zero()),
+                    false => (
+                        self.fees(progress).1,
+                        self.liquidity
+                            .as_ref()
+                            .map_or(Uint128::zero(), |liquidity| liquidity.amounts(self.raised(progress, now)).1),
+                    ),
+                };
+
+                (progress.token_sold - progress.token_claimed)
+                    + (self.owner_allocation - progress.owner_token_claimed)
+                    + token_fee
+                    + token_liquidity
+            }
+        }
+    }
+
+    /// Platform fees charged on settlement, in currency and in sale token.
+    pub fn fees(&self, progress: &SaleProgress) -> (Uint128, Uint128) {
+        (
+            progress.cur_raised.multiply_ratio(self.fee_percentage, FEE_DENOMINATOR),
+            progress.token_sold.multiply_ratio(self.token_fee_percentage, FEE_DENOMINATOR),
+        )
+    }
+
+    /// Referral reward worth `percentage` of the base of a `cur_amount` contribution.
+    pub fn referral_reward(&self, cur_amount: Uint128, percentage: Uint128) -> Uint128 {
+        let base = match self.referral.base {
+            ReferralBase::PlatformFee => cur_amount.multiply_ratio(self.fee_percentage, FEE_DENOMINATOR),
+            ReferralBase::Raised => cur_amount,
+        };
+
+        base.multiply_ratio(percentage, FEE_DENOMINATOR)
+    }
+
+    /// Upper bound of the currency finally paid by the referred participants,
+    /// their rewards are credited from it one by one as they claim. It never
+    /// exceeds the raise, so the reward stays within the validated share.
+    pub fn referred_raised(&self, progress: &SaleProgress, now: u64) -> Uint128 {
+        // every participant may pay one unit more than the pro-rata share through rounding
+        let referred = Uint128::from(progress.referred);
+        let raised = match &self.sale_type {
+            SaleType::FixedPrice | SaleType::BondingCurve { .. } => progress.referred_spent,
+            SaleType::Overflow => self.accepted_share(progress, progress.referred_spent, now) + referred,
+            SaleType::DutchAuction { .. } => {
+                progress.referred_token.mul_ceil(self.clearing_price(progress, now)) + referred
+            }
+            SaleType::BatchAuction { .. } => progress.referred_filled,
+        };
+
+        raised.min(progress.referred_spent).min(self.raised(progress, now))
+    }
+
+    /// Contribution referral rewards held back on settlement but not credited,
+    /// due once every referred participant has settled.
+    pub fn referral_surplus(&self, progress: &SaleProgress) -> Uint128 {
+        match progress.is_excess_sent && progress.referred_settled == progress.referred {
+            true => progress.referral_held.saturating_sub(progress.referral_paid),
+            false => Uint128::zero(),
+        }
+    }
+
+    /// Amount of the owner allocation released at `now`.
+    pub fn owner_vested(&self, now: u64) -> Uint128 {
+        match &self.owner_vesting {
+            Some(vesting) => vesting.vested(self.owner_allocation, self.end, now),
+            None => self.owner_allocation,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SaleStatus {
     NotStarted,
-    Ongoing,
+    /// `round` is the index of the open round, none between two rounds
+    Ongoing { round: Option<u32> },
     Ended,
     Filled,
     Failed,
+    Cancelled,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct SaleProgress {
+    pub token_escrowed: Uint128,
+    // --
     pub token_sold: Uint128,
     pub cur_raised: Uint128,
+    pub round_raised: Vec<Uint128>,
+    pub participants: u32,
+    // price of the last auction purchase, or the batch auction clearing price
+    pub clearing_price: Decimal,
+    // batch auction settlement: last bid filled in full, currency of the bids
+    // filled in full and the partly filled bid with its accepted currency
+    pub bid_cursor: Option<(Uint128, Addr)>,
+    pub bid_total: Uint128,
+    pub marginal_bid: Option<(Addr, Uint128)>,
+    pub is_auction_settled: bool,
+    // lottery: registered tickets and the running hash of their holders, operator
+    // seed commitment, revealed seed mixed with the tickets hash and tickets drawn so far
+    pub tickets: u32,
+    pub tickets_hash: Binary,
+    pub seed_commitment: Option<String>,
+    pub seed: Option<Binary>,
+    pub tickets_drawn: u32,
+    // sum of the tier weights registered for the guaranteed rounds
+    pub tier_weight_total: u64,
     // --
     pub token_claimed: Uint128,
+    pub owner_token_claimed: Uint128,
     // --
+    pub is_cancelled: bool,
     pub is_excess_sent: bool,
     pub cur_excess: Uint128,
     pub token_excess: Uint128,
+    // --
+    pub cur_fee: Uint128,
+    pub token_fee: Uint128,
+    pub referral_reward: Uint128,
+    // contribution referral rewards held back on settlement and those credited
+    // so far, the surplus is released once every referred participant settled
+    pub referral_held: Uint128,
+    pub referral_paid: Uint128,
+    pub referred_settled: u32,
+    // referred participants, the currency they committed, the tokens they
+    // bought and for batch auctions the currency of their bids filled
+    pub referred: u32,
+    pub referred_spent: Uint128,
+    pub referred_token: Uint128,
+    pub referred_filled: Uint128,
+    // paired into the DEX pool on settlement
+    pub cur_liquidity: Uint128,
+    pub token_liquidity: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
 pub struct SaleProgressPersonal {
-    pub is_claimed: bool,
+    pub token_claimed: Uint128,
     pub is_refunded: bool,
     pub token_got: Uint128,
     pub cur_spent: Uint128,
+    // set on the first claim, once the allocation is final
+    pub is_settled: bool,
+    pub cur_returned: Uint128,
+    // batch auction max price
+    pub bid_price: Decimal,
 }
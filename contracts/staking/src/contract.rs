@@ -0,0 +1,57 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cw2::set_contract_version;
+
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::querier::{query_config, query_staked_at, query_total_staked_at, query_claims};
+use crate::state::{CONFIG, Config};
+use crate::handler::{execute_receive, execute_unstake, execute_claim_unbonded};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:absolute-fi-staking";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let config = Config {
+        token: deps.api.addr_validate(&msg.token)?,
+        unbonding_period: msg.unbonding_period,
+    };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("token", config.token)
+        .add_attribute("unbonding_period", config.unbonding_period.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Unstake{ amount } => execute_unstake(deps, env, info, amount),
+        ExecuteMsg::ClaimUnbonded{} => execute_claim_unbonded(deps, env, info),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::StakedAt { address, height_or_time } => to_json_binary(&query_staked_at(deps, address, height_or_time)?),
+        QueryMsg::TotalStakedAt { height_or_time } => to_json_binary(&query_total_staked_at(deps, height_or_time)?),
+        QueryMsg::Claims { address } => to_json_binary(&query_claims(deps, address)?),
+    }
+}
@@ -0,0 +1,29 @@
+use cosmwasm_std::{StdError, Uint128};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Amount cannot be zero")]
+    ZeroAmount,
+
+    #[error("Unstake amount exceeds the staked {0}")]
+    AboveStaked(Uint128),
+
+    #[error("No unbonded tokens to claim yet")]
+    NothingToClaim,
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl ContractError {
+    pub fn custom(description: impl Into<String>) -> Self {
+        Self::Custom(description.into())
+    }
+}
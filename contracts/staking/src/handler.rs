@@ -0,0 +1,81 @@
+use cosmwasm_std::{from_json, Addr, DepsMut, Env, MessageInfo, Response, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw_asset::Asset;
+use cw_utils::Expiration;
+
+use crate::{
+    msg::ReceiveMsg,
+    state::{update_stake, CLAIMS, CONFIG, STAKE},
+    ContractError,
+};
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    (info.sender == config.token)
+        .then_some(())
+        .ok_or(ContractError::Unauthorized)?;
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::Stake {} => execute_stake(deps, env, sender, wrapper.amount),
+    }
+}
+
+pub fn execute_stake(deps: DepsMut, env: Env, sender: Addr, amount: Uint128) -> Result<Response, ContractError> {
+    (!amount.is_zero())
+        .then_some(())
+        .ok_or(ContractError::ZeroAmount)?;
+
+    let staked = update_stake(deps.storage, &env.block, &sender, |staked| Ok(staked + amount))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "stake")
+        .add_attribute("address", sender)
+        .add_attribute("amount", amount)
+        .add_attribute("staked", staked))
+}
+
+/// Removes `amount` from the sender stake at once, the tokens are claimable
+/// after the unbonding period.
+pub fn execute_unstake(deps: DepsMut, env: Env, info: MessageInfo, amount: Uint128) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    (!amount.is_zero())
+        .then_some(())
+        .ok_or(ContractError::ZeroAmount)?;
+
+    let staked = STAKE.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    (amount <= staked)
+        .then_some(())
+        .ok_or(ContractError::AboveStaked(staked))?;
+
+    let staked = update_stake(deps.storage, &env.block, &info.sender, |staked| Ok(staked - amount))?;
+    let release_at = env.block.time.plus_seconds(config.unbonding_period);
+    CLAIMS.create_claim(deps.storage, &info.sender, amount, Expiration::AtTime(release_at))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "unstake")
+        .add_attribute("address", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("staked", staked)
+        .add_attribute("release_at", release_at.seconds().to_string()))
+}
+
+pub fn execute_claim_unbonded(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let amount = CLAIMS.claim_tokens(deps.storage, &info.sender, &env.block, None)?;
+    (!amount.is_zero())
+        .then_some(())
+        .ok_or(ContractError::NothingToClaim)?;
+
+    Ok(Response::new()
+        .add_message(Asset::cw20(config.token, amount).transfer_msg(&info.sender)?)
+        .add_attribute("action", "claim_unbonded")
+        .add_attribute("address", info.sender)
+        .add_attribute("amount", amount))
+}
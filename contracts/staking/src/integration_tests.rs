@@ -0,0 +1,243 @@
+use cosmwasm_std::{to_json_binary, Addr, Empty, Uint128};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw_controllers::ClaimsResponse;
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ReceiveMsg, StakedResponse};
+use crate::state::HeightOrTime;
+use crate::ContractError;
+
+const ADMIN: &str = "admin";
+const STAKER: &str = "staker";
+const OTHER: &str = "other";
+const UNBONDING_PERIOD: u64 = 7 * 24 * 60 * 60;
+
+fn staking_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    ))
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+struct Suite {
+    app: App,
+    staking: Addr,
+    token: Addr,
+}
+
+impl Suite {
+    fn new() -> Self {
+        let mut app = App::default();
+
+        let cw20_code_id = app.store_code(cw20_contract());
+        let token = app
+            .instantiate_contract(
+                cw20_code_id,
+                Addr::unchecked(ADMIN),
+                &Cw20InstantiateMsg {
+                    name: "Platform Token".to_string(),
+                    symbol: "PLAT".to_string(),
+                    decimals: 6,
+                    initial_balances: [STAKER, OTHER]
+                        .iter()
+                        .map(|address| Cw20Coin {
+                            address: address.to_string(),
+                            amount: Uint128::new(1_000),
+                        })
+                        .collect(),
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "platform token",
+                None,
+            )
+            .unwrap();
+
+        let staking_code_id = app.store_code(staking_contract());
+        let staking = app
+            .instantiate_contract(
+                staking_code_id,
+                Addr::unchecked(ADMIN),
+                &InstantiateMsg {
+                    token: token.to_string(),
+                    unbonding_period: UNBONDING_PERIOD,
+                },
+                &[],
+                "staking",
+                None,
+            )
+            .unwrap();
+
+        Self { app, staking, token }
+    }
+
+    fn next_block(&mut self, seconds: u64) {
+        self.app.update_block(|block| {
+            block.height += 1;
+            block.time = block.time.plus_seconds(seconds);
+        });
+    }
+
+    fn stake(&mut self, sender: &str, amount: u128) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.token.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: self.staking.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_json_binary(&ReceiveMsg::Stake {}).unwrap(),
+            },
+            &[],
+        )
+    }
+
+    fn execute(&mut self, sender: &str, msg: &ExecuteMsg) -> anyhow::Result<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.staking.clone(), msg, &[])
+    }
+
+    fn staked_at(&self, address: &str, height_or_time: Option<HeightOrTime>) -> u128 {
+        let res: StakedResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.staking,
+                &QueryMsg::StakedAt {
+                    address: address.to_string(),
+                    height_or_time,
+                },
+            )
+            .unwrap();
+        res.amount.u128()
+    }
+
+    fn balance(&self, address: &str) -> u128 {
+        let res: BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.token,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+}
+
+#[test]
+fn stake_history_answers_snapshots_by_height_and_time() {
+    let mut suite = Suite::new();
+    let start = suite.app.block_info();
+
+    suite.stake(STAKER, 300).unwrap();
+    suite.stake(OTHER, 100).unwrap();
+    // a stake counts from the next block only
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Height(start.height))), 0);
+    assert_eq!(suite.staked_at(STAKER, None), 300);
+
+    suite.next_block(60);
+    suite.stake(STAKER, 200).unwrap();
+    suite.next_block(60);
+    suite.execute(STAKER, &ExecuteMsg::Unstake { amount: Uint128::new(400) }).unwrap();
+    suite.next_block(60);
+
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Height(start.height + 1))), 300);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Height(start.height + 2))), 500);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Height(start.height + 3))), 100);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Time(start.time.seconds() + 90))), 500);
+    assert_eq!(suite.staked_at(OTHER, Some(HeightOrTime::Time(start.time.seconds() + 90))), 100);
+    // a time counts the changes of the blocks strictly before it
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Time(start.time.seconds()))), 0);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Time(start.time.seconds() + 1))), 300);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Time(start.time.seconds() + 120))), 500);
+    assert_eq!(suite.staked_at(STAKER, Some(HeightOrTime::Time(start.time.seconds() + 121))), 100);
+
+    let res: StakedResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.staking,
+            &QueryMsg::TotalStakedAt {
+                height_or_time: Some(HeightOrTime::Height(start.height + 2)),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::new(600));
+    let res: StakedResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.staking,
+            &QueryMsg::TotalStakedAt {
+                height_or_time: Some(HeightOrTime::Time(start.time.seconds() + 121)),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.amount, Uint128::new(200));
+}
+
+#[test]
+fn unstaked_tokens_are_claimable_after_unbonding() {
+    let mut suite = Suite::new();
+    suite.stake(STAKER, 500).unwrap();
+
+    let err = suite
+        .execute(STAKER, &ExecuteMsg::Unstake { amount: Uint128::new(501) })
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::AboveStaked(Uint128::new(500)).to_string()
+    );
+    suite.execute(STAKER, &ExecuteMsg::Unstake { amount: Uint128::new(200) }).unwrap();
+    let res: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.staking, &QueryMsg::Claims { address: STAKER.to_string() })
+        .unwrap();
+    assert_eq!(res.claims.len(), 1);
+    assert_eq!(res.claims[0].amount, Uint128::new(200));
+
+    suite.next_block(UNBONDING_PERIOD - 1);
+    let err = suite.execute(STAKER, &ExecuteMsg::ClaimUnbonded {}).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::NothingToClaim.to_string()
+    );
+
+    suite.next_block(1);
+    suite.execute(STAKER, &ExecuteMsg::ClaimUnbonded {}).unwrap();
+    assert_eq!(suite.balance(STAKER), 700);
+    assert_eq!(suite.staked_at(STAKER, None), 300);
+
+    // only the platform token can be staked
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked(STAKER),
+            suite.staking.clone(),
+            &ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: STAKER.to_string(),
+                amount: Uint128::new(100),
+                msg: to_json_binary(&ReceiveMsg::Stake {}).unwrap(),
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap().to_string(),
+        ContractError::Unauthorized.to_string()
+    );
+}
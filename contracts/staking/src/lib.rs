@@ -0,0 +1,9 @@
+pub mod contract;
+pub mod error;
+#[cfg(test)]
+mod integration_tests;
+pub mod msg;
+pub mod state;
+pub mod handler;
+pub use crate::error::ContractError;
+pub mod querier;
@@ -0,0 +1,35 @@
+use cosmwasm_std::{Deps, StdResult};
+use cw_controllers::ClaimsResponse;
+
+use crate::{
+    msg::{ConfigResponse, StakedResponse},
+    state::{staked_at, total_staked_at, HeightOrTime, CLAIMS, CONFIG},
+};
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(ConfigResponse {
+        token: config.token,
+        unbonding_period: config.unbonding_period,
+    })
+}
+
+pub fn query_staked_at(deps: Deps, address: String, height_or_time: Option<HeightOrTime>) -> StdResult<StakedResponse> {
+    let address = deps.api.addr_validate(&address)?;
+
+    Ok(StakedResponse {
+        amount: staked_at(deps.storage, &address, height_or_time)?,
+    })
+}
+
+pub fn query_total_staked_at(deps: Deps, height_or_time: Option<HeightOrTime>) -> StdResult<StakedResponse> {
+    Ok(StakedResponse {
+        amount: total_staked_at(deps.storage, height_or_time)?,
+    })
+}
+
+pub fn query_claims(deps: Deps, address: String) -> StdResult<ClaimsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    CLAIMS.query_claims(deps, &address)
+}
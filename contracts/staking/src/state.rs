@@ -0,0 +1,111 @@
+use cosmwasm_std::{Addr, BlockInfo, Order, StdResult, Storage, Uint128};
+use cw_controllers::Claims;
+use cw_storage_plus::{Bound, Item, Map};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// current stakes
+pub const STAKE: Map<&Addr, Uint128> = Map::new("stake");
+pub const TOTAL_STAKED: Item<Uint128> = Item::new("total_staked");
+// stake after the last change of each block, keyed by height
+pub const STAKE_HISTORY: Map<(&Addr, u64), Checkpoint> = Map::new("stake_history");
+pub const TOTAL_HISTORY: Map<u64, Checkpoint> = Map::new("total_history");
+// last block changing a stake at each time, time -> height
+pub const HEIGHT_AT_TIME: Map<u64, u64> = Map::new("height_at_time");
+
+// unstaked tokens waiting for the unbonding period
+pub const CLAIMS: Claims = Claims::new("claims");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    /// cw20 platform token accepted for staking
+    pub token: Addr,
+    /// seconds unstaked tokens stay locked before they can be claimed
+    pub unbonding_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Checkpoint {
+    pub time: u64,
+    pub amount: Uint128,
+}
+
+/// Point of the stake history, a stake counts from the block after it changed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeightOrTime {
+    Height(u64),
+    /// seconds
+    Time(u64),
+}
+
+impl HeightOrTime {
+    /// First height whose changes do not count at this point, times being
+    /// mapped to heights through the index of the blocks changing stakes.
+    fn end_height(&self, storage: &dyn Storage) -> StdResult<u64> {
+        match self {
+            HeightOrTime::Height(height) => Ok(*height),
+            HeightOrTime::Time(time) => Ok(HEIGHT_AT_TIME
+                .range(storage, None, Some(Bound::exclusive(*time)), Order::Descending)
+                .next()
+                .transpose()?
+                .map_or(0, |(_, height)| height + 1)),
+        }
+    }
+}
+
+/// Applies `action` to the stake of `address` and to the total, recording both
+/// in the history at the current block.
+pub fn update_stake(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    address: &Addr,
+    action: impl Fn(Uint128) -> StdResult<Uint128>,
+) -> StdResult<Uint128> {
+    let staked = action(STAKE.may_load(storage, address)?.unwrap_or_default())?;
+    let total = action(TOTAL_STAKED.may_load(storage)?.unwrap_or_default())?;
+    let time = block.time.seconds();
+
+    STAKE.save(storage, address, &staked)?;
+    TOTAL_STAKED.save(storage, &total)?;
+    STAKE_HISTORY.save(storage, (address, block.height), &Checkpoint { time, amount: staked })?;
+    TOTAL_HISTORY.save(storage, block.height, &Checkpoint { time, amount: total })?;
+    HEIGHT_AT_TIME.save(storage, time, &block.height)?;
+
+    Ok(staked)
+}
+
+/// Stake of `address` at `point`, the current one when unset.
+pub fn staked_at(storage: &dyn Storage, address: &Addr, point: Option<HeightOrTime>) -> StdResult<Uint128> {
+    let point = match point {
+        Some(point) => point,
+        None => return Ok(STAKE.may_load(storage, address)?.unwrap_or_default()),
+    };
+
+    let max = Bound::exclusive(point.end_height(storage)?);
+    let checkpoint = STAKE_HISTORY
+        .prefix(address)
+        .range(storage, None, Some(max), Order::Descending)
+        .next()
+        .transpose()?;
+
+    Ok(checkpoint.map_or(Uint128::zero(), |(_, checkpoint)| checkpoint.amount))
+}
+
+/// Total staked at `point`, the current one when unset.
+pub fn total_staked_at(storage: &dyn Storage, point: Option<HeightOrTime>) -> StdResult<Uint128> {
+    let point = match point {
+        Some(point) => point,
+        None => return Ok(TOTAL_STAKED.may_load(storage)?.unwrap_or_default()),
+    };
+
+    let max = Bound::exclusive(point.end_height(storage)?);
+    let checkpoint = TOTAL_HISTORY
+        .range(storage, None, Some(max), Order::Descending)
+        .next()
+        .transpose()?;
+
+    Ok(checkpoint.map_or(Uint128::zero(), |(_, checkpoint)| checkpoint.amount))
+}